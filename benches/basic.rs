@@ -4,21 +4,31 @@ extern crate rand;
 
 use criterion::Benchmark;
 use criterion::Criterion;
-use lb_vrf::lbvrf::LBVRF;
-use lb_vrf::param::Param;
-use lb_vrf::poly::PolyArith;
+use lb_vrf::lbvrf::{self, PreparedVerifier, LBVRF};
+use lb_vrf::param::{HighSecurity, Param};
+use lb_vrf::poly::{PolyArith, ShortSample, TrinaryMul, UniformSample};
 use lb_vrf::poly256::*;
+use lb_vrf::poly32::{self, Poly32};
+use lb_vrf::reduce::*;
 use lb_vrf::VRF;
 use rand::RngCore;
 use std::time::Duration;
 
-criterion_group!(basic, ot_lbvrf, trinary_poly);
+criterion_group!(
+    basic,
+    ot_lbvrf,
+    batch_verify,
+    trinary_poly,
+    ntt_poly,
+    poly32_mul,
+    reduction
+);
 criterion_main!(basic);
 
 fn ot_lbvrf(c: &mut Criterion) {
     let mut rng = rand::thread_rng();
     let mut seed = [0u8; 32];
-    let bench_str = format!("parameter generation");
+    let bench_str = "parameter generation".to_string();
     let bench = Benchmark::new(bench_str, move |b| {
         b.iter(|| {
             rng.fill_bytes(&mut seed);
@@ -27,31 +37,64 @@ fn ot_lbvrf(c: &mut Criterion) {
     });
 
     let param: Param = <LBVRF as VRF>::paramgen(seed).unwrap();
-    let bench_str = format!("key generation");
+    let bench_str = "key generation".to_string();
+    let pp = param.clone();
     let bench = bench.with_function(bench_str, move |b| {
         b.iter(|| {
             rng.fill_bytes(&mut seed);
-            <LBVRF as VRF>::keygen(seed, param).unwrap();
+            <LBVRF as VRF>::keygen(seed, &pp).unwrap();
         });
     });
 
-    let (pk, sk) = <LBVRF as VRF>::keygen(seed, param).unwrap();
+    let (pk, sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
     let message = "this is a message that vrf signs";
 
-    let bench_str = format!("proof");
+    let bench_str = "proof".to_string();
+    let pp = param.clone();
+    let (pub_key, key) = (pk.clone(), sk.clone());
     let bench = bench.with_function(bench_str, move |b| {
         b.iter(|| {
             rng.fill_bytes(&mut seed);
-            <LBVRF as VRF>::prove(message, param, pk, sk, seed).unwrap();
+            <LBVRF as VRF>::prove(message, &pp, &pub_key, &key, seed).unwrap();
         });
     });
 
-    let bench_str = format!("verify");
-    let proof = <LBVRF as VRF>::prove(message, param, pk, sk, seed).unwrap();
+    let bench_str = "verify".to_string();
+    let proof = <LBVRF as VRF>::prove(message, &param, &pk, &sk, seed).unwrap();
+    let pp = param.clone();
+    let (pub_key, pi) = (pk.clone(), proof.clone());
+    let bench = bench.with_function(bench_str, move |b| {
+        b.iter(|| {
+            let res = <LBVRF as VRF>::verify(message, &pp, &pub_key, &pi).unwrap();
+            assert!(res.is_some());
+        });
+    });
+
+    let bench_str = "verify with prepared verifier".to_string();
+    let bench = bench.with_function(bench_str, move |b| {
+        let verifier = PreparedVerifier::new(&param, &pk).unwrap();
+        b.iter(|| {
+            let res = verifier.verify(message, &proof).unwrap();
+            assert!(res.is_some());
+        });
+    });
+
+    let param: Param<HighSecurity> = <LBVRF<HighSecurity> as VRF>::paramgen(seed).unwrap();
+    let (pk, sk) = <LBVRF<HighSecurity> as VRF>::keygen(seed, &param).unwrap();
+    let bench_str = "proof (high security)".to_string();
+    let (pp, pub_key, key) = (param.clone(), pk.clone(), sk.clone());
     let bench = bench.with_function(bench_str, move |b| {
         b.iter(|| {
             rng.fill_bytes(&mut seed);
-            let res = <LBVRF as VRF>::verify(message, param, pk, proof).unwrap();
+            <LBVRF<HighSecurity> as VRF>::prove(message, &pp, &pub_key, &key, seed).unwrap();
+        });
+    });
+
+    let bench_str = "verify (high security)".to_string();
+    let proof = <LBVRF<HighSecurity> as VRF>::prove(message, &param, &pk, &sk, seed).unwrap();
+    let bench = bench.with_function(bench_str, move |b| {
+        b.iter(|| {
+            let res = <LBVRF<HighSecurity> as VRF>::verify(message, &param, &pk, &proof).unwrap();
             assert!(res.is_some());
         });
     });
@@ -62,10 +105,48 @@ fn ot_lbvrf(c: &mut Criterion) {
     c.bench("one time lbvrf", bench);
 }
 
+// throughput of verifying a block of proofs, one key per proof
+fn batch_verify(c: &mut Criterion) {
+    const BATCH: usize = 128;
+    let param: Param = <LBVRF as VRF>::paramgen([0u8; 32]).unwrap();
+    let mut batch = vec![];
+    for i in 0..BATCH {
+        let seed = [i as u8; 32];
+        let (pk, sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
+        let proof = <LBVRF as VRF>::prove(seed, &param, &pk, &sk, seed).unwrap();
+        batch.push((seed, pk, proof));
+    }
+
+    let bench_str = format!("verify loop ({} proofs)", BATCH);
+    let (pp, items) = (param.clone(), batch.clone());
+    let bench = Benchmark::new(bench_str, move |b| {
+        b.iter(|| {
+            for (message, pk, proof) in items.iter() {
+                let res = <LBVRF as VRF>::verify(message, &pp, pk, proof).unwrap();
+                assert!(res.is_some());
+            }
+        });
+    });
+
+    let bench_str = format!("verify_batch ({} proofs)", BATCH);
+    let bench = bench.with_function(bench_str, move |b| {
+        let items: Vec<_> = batch.iter().map(|(m, pk, p)| (m, pk, p)).collect();
+        b.iter(|| {
+            let res = lbvrf::verify_batch(&param, &items);
+            assert!(res.iter().all(|r| r.is_ok()));
+        });
+    });
+
+    let bench = bench.warm_up_time(Duration::from_millis(1000));
+    let bench = bench.measurement_time(Duration::from_millis(5000));
+    let bench = bench.sample_size(20);
+    c.bench("batch verification", bench);
+}
+
 fn trinary_poly(c: &mut Criterion) {
     let mut rng = rand::thread_rng();
 
-    let bench_str = format!("trinary using trinary");
+    let bench_str = "trinary using trinary".to_string();
     let bench = Benchmark::new(bench_str, move |d| {
         let a = Poly256::uniform_random(&mut rng);
         let b = Poly256::rand_trinary(&mut rng);
@@ -74,7 +155,7 @@ fn trinary_poly(c: &mut Criterion) {
         });
     });
 
-    let bench_str = format!("trinary normal");
+    let bench_str = "trinary normal".to_string();
     let bench = bench.with_function(bench_str, move |d| {
         let a = Poly256::uniform_random(&mut rng);
         let b = Poly256::rand_trinary(&mut rng);
@@ -83,7 +164,7 @@ fn trinary_poly(c: &mut Criterion) {
         });
     });
 
-    let bench_str = format!("uniform normal");
+    let bench_str = "uniform normal".to_string();
     let bench = bench.with_function(bench_str, move |d| {
         let a = Poly256::uniform_random(&mut rng);
         let b = Poly256::uniform_random(&mut rng);
@@ -92,7 +173,7 @@ fn trinary_poly(c: &mut Criterion) {
         });
     });
 
-    let bench_str = format!("uniform karatsuba");
+    let bench_str = "uniform karatsuba".to_string();
     let bench = bench.with_function(bench_str, move |d| {
         let a = Poly256::uniform_random(&mut rng);
         let b = Poly256::uniform_random(&mut rng);
@@ -106,3 +187,161 @@ fn trinary_poly(c: &mut Criterion) {
     let bench = bench.sample_size(100);
     c.bench("poly mul", bench);
 }
+
+fn ntt_poly(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+
+    let bench_str = "uniform school book".to_string();
+    let bench = Benchmark::new(bench_str, move |d| {
+        let a = Poly256::uniform_random(&mut rng);
+        let b = Poly256::uniform_random(&mut rng);
+        d.iter(|| {
+            school_book(&a, &b);
+        });
+    });
+
+    let bench_str = "uniform ntt".to_string();
+    let bench = bench.with_function(bench_str, move |d| {
+        let a = Poly256::uniform_random(&mut rng);
+        let b = Poly256::uniform_random(&mut rng);
+        d.iter(|| {
+            Poly256::mul(&a, &b);
+        });
+    });
+
+    let bench_str = "inner product school book".to_string();
+    let bench = bench.with_function(bench_str, move |d| {
+        let a: Vec<Poly256> = (0..9).map(|_| Poly256::uniform_random(&mut rng)).collect();
+        let b: Vec<Poly256> = (0..9).map(|_| Poly256::rand_mod_beta(&mut rng)).collect();
+        d.iter(|| {
+            let mut res = Poly256::zero();
+            for i in 0..9 {
+                res.add_assign(&school_book(&a[i], &b[i]));
+            }
+            res.normalized();
+        });
+    });
+
+    let bench_str = "inner product ntt".to_string();
+    let bench = bench.with_function(bench_str, move |d| {
+        let a: Vec<Poly256> = (0..9).map(|_| Poly256::uniform_random(&mut rng)).collect();
+        let b: Vec<Poly256> = (0..9).map(|_| Poly256::rand_mod_beta(&mut rng)).collect();
+        d.iter(|| {
+            poly256_inner_product(&a, &b);
+        });
+    });
+
+    let bench_str = "inner product ntt with precomputed matrix".to_string();
+    let bench = bench.with_function(bench_str, move |d| {
+        let a: Vec<Poly256> = (0..9)
+            .map(|_| Poly256::uniform_random(&mut rng).ntt())
+            .collect();
+        let b: Vec<Poly256> = (0..9).map(|_| Poly256::rand_mod_beta(&mut rng)).collect();
+        d.iter(|| {
+            let b_ntt: Vec<Poly256> = b.iter().map(|x| x.ntt()).collect();
+            poly256_inner_product_ntt(&a, &b_ntt);
+        });
+    });
+
+    let bench = bench.warm_up_time(Duration::from_millis(1000));
+    let bench = bench.measurement_time(Duration::from_millis(5000));
+    let bench = bench.sample_size(100);
+    c.bench("ntt", bench);
+}
+
+fn poly32_mul(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+
+    let bench_str = "school book".to_string();
+    let bench = Benchmark::new(bench_str, move |d| {
+        let a = Poly32::uniform_random(&mut rng);
+        let b = Poly32::uniform_random(&mut rng);
+        d.iter(|| {
+            poly32::school_book(&a, &b);
+        });
+    });
+
+    let bench_str = "karatsuba".to_string();
+    let bench = bench.with_function(bench_str, move |d| {
+        let a = Poly32::uniform_random(&mut rng);
+        let b = Poly32::uniform_random(&mut rng);
+        d.iter(|| {
+            Poly32::mul_karatsuba(&a, &b);
+        });
+    });
+
+    let bench_str = "inner product school book".to_string();
+    let bench = bench.with_function(bench_str, move |d| {
+        let a: Vec<Poly32> = (0..9).map(|_| Poly32::uniform_random(&mut rng)).collect();
+        let b: Vec<Poly32> = (0..9).map(|_| Poly32::uniform_random(&mut rng)).collect();
+        d.iter(|| {
+            let mut res = Poly32::zero();
+            for i in 0..9 {
+                res.add_assign(&poly32::school_book(&a[i], &b[i]));
+            }
+            res.normalized();
+        });
+    });
+
+    let bench_str = "inner product".to_string();
+    let bench = bench.with_function(bench_str, move |d| {
+        let a: Vec<Poly32> = (0..9).map(|_| Poly32::uniform_random(&mut rng)).collect();
+        let b: Vec<Poly32> = (0..9).map(|_| Poly32::uniform_random(&mut rng)).collect();
+        d.iter(|| {
+            poly32::poly32_inner_product(&a, &b);
+        });
+    });
+
+    let bench = bench.warm_up_time(Duration::from_millis(1000));
+    let bench = bench.measurement_time(Duration::from_millis(5000));
+    let bench = bench.sample_size(100);
+    c.bench("poly32 mul", bench);
+}
+
+fn reduction(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let q = lb_vrf::param::Q as i64;
+    let root = to_montgomery_q(316072) as i64;
+
+    // products of two elements mod q, as they occur in the NTT
+    let bench_str = "% on i64".to_string();
+    let bench = Benchmark::new(bench_str, move |d| {
+        let a: Vec<i64> = (0..256).map(|_| rng.next_u32() as i64 % q).collect();
+        d.iter(|| a.iter().map(|x| x * 316072 % q).sum::<i64>());
+    });
+
+    let bench_str = "montgomery".to_string();
+    let bench = bench.with_function(bench_str, move |d| {
+        let a: Vec<i32> = (0..256)
+            .map(|_| (rng.next_u32() as i64 % q) as i32)
+            .collect();
+        d.iter(|| {
+            a.iter()
+                .map(|x| montgomery_reduce_q(*x as i64 * root) as i64)
+                .sum::<i64>()
+        });
+    });
+
+    let bench_str = "barrett 64".to_string();
+    let bench = bench.with_function(bench_str, move |d| {
+        let a: Vec<i32> = (0..256)
+            .map(|_| (rng.next_u32() as i64 % q) as i32)
+            .collect();
+        d.iter(|| {
+            a.iter()
+                .map(|x| reduce_q(*x as i64 * 316072) as i64)
+                .sum::<i64>()
+        });
+    });
+
+    let bench_str = "barrett 32".to_string();
+    let bench = bench.with_function(bench_str, move |d| {
+        let a: Vec<i32> = (0..256).map(|_| rng.next_u32() as i32).collect();
+        d.iter(|| a.iter().map(|x| barrett_reduce_q(*x) as i64).sum::<i64>());
+    });
+
+    let bench = bench.warm_up_time(Duration::from_millis(1000));
+    let bench = bench.measurement_time(Duration::from_millis(5000));
+    let bench = bench.sample_size(100);
+    c.bench("reduction", bench);
+}
@@ -0,0 +1,307 @@
+// this file implements AVX2 kernels for the arithmetics over Z_q[x]/(x^256 + 1)
+//
+// each kernel returns exactly the same canonical result as its portable
+// counterpart in `ntt.rs`, `poly256.rs` and `lbvrf.rs`; only the
+// intermediate representatives may differ. the kernels are selected at
+// runtime if the CPU supports AVX2, see `enabled`.
+//
+// 32 bit products are formed with `_mm256_mul_epi32`, which multiplies the
+// even lanes only; the odd lanes are handled by shifting them down first.
+
+use crate::ntt::{rearrange, FWD_LAYER_ROOTS, INV_LAYER_ROOTS, ROOTS_MONT, UNTWIST_MONT};
+use crate::param::Q;
+use crate::poly256::Poly256;
+use crate::reduce::{Q_BARRETT32, Q_INV, Q_MONT2};
+use std::arch::x86_64::*;
+#[cfg(test)]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(test)]
+use std::sync::Mutex;
+
+// the switch to the portable code in the differential tests; it is process
+// wide so that it reaches the threads of the `parallel` feature
+#[cfg(test)]
+static FORCE_SCALAR: AtomicBool = AtomicBool::new(false);
+// number of times the portable code was selected because of FORCE_SCALAR
+#[cfg(test)]
+static SCALAR_HITS: AtomicUsize = AtomicUsize::new(0);
+// the differential tests run one at a time, so that each side sees its own
+// setting of the switch
+#[cfg(test)]
+static DIFFERENTIAL: Mutex<()> = Mutex::new(());
+
+/// whether the AVX2 kernels are used
+#[inline]
+pub(crate) fn enabled() -> bool {
+    #[cfg(test)]
+    {
+        if FORCE_SCALAR.load(Ordering::SeqCst) {
+            SCALAR_HITS.fetch_add(1, Ordering::SeqCst);
+            return false;
+        }
+    }
+    is_x86_feature_detected!("avx2")
+}
+
+// clears the switch, even if the test panics
+#[cfg(test)]
+struct ScalarGuard;
+
+#[cfg(test)]
+impl Drop for ScalarGuard {
+    fn drop(&mut self) {
+        FORCE_SCALAR.store(false, Ordering::SeqCst);
+    }
+}
+
+/// run f with the AVX2 kernels, then with the portable code only, and check
+/// that both runs return the same value and that the portable code was used;
+/// used by the differential tests
+#[cfg(test)]
+pub(crate) fn assert_scalar_eq<T: PartialEq + std::fmt::Debug>(f: impl Fn() -> T) -> T {
+    let _lock = DIFFERENTIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let simd = f();
+    let scalar = {
+        let _guard = ScalarGuard;
+        SCALAR_HITS.store(0, Ordering::SeqCst);
+        FORCE_SCALAR.store(true, Ordering::SeqCst);
+        f()
+    };
+    assert!(
+        SCALAR_HITS.load(Ordering::SeqCst) > 0,
+        "the portable code was not used"
+    );
+    assert_eq!(simd, scalar);
+    simd
+}
+
+/// Montgomery multiplication: a * b * 2^-32 mod q in (-q, q),
+/// for |a * b| < q * 2^31
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn montgomery_mul(a: __m256i, b: __m256i) -> __m256i {
+    let q = _mm256_set1_epi32(Q);
+    let qinv = _mm256_set1_epi32(Q_INV);
+    // even lanes
+    let prod = _mm256_mul_epi32(a, b);
+    let t = _mm256_mul_epi32(prod, qinv);
+    let even = _mm256_sub_epi64(prod, _mm256_mul_epi32(t, q));
+    // odd lanes
+    let prod = _mm256_mul_epi32(_mm256_srli_epi64(a, 32), _mm256_srli_epi64(b, 32));
+    let t = _mm256_mul_epi32(prod, qinv);
+    let odd = _mm256_sub_epi64(prod, _mm256_mul_epi32(t, q));
+    // the results are in the high halves of the 64 bit lanes
+    _mm256_blend_epi32(_mm256_srli_epi64(even, 32), odd, 0b1010_1010)
+}
+
+/// Barrett reduction: any i32 to a representative in (-q, q)
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn barrett_reduce(a: __m256i) -> __m256i {
+    let v = _mm256_set1_epi32(Q_BARRETT32 as i32);
+    let round = _mm256_set1_epi64x(1 << 56);
+    let even = _mm256_add_epi64(_mm256_mul_epi32(a, v), round);
+    let odd = _mm256_add_epi64(_mm256_mul_epi32(_mm256_srli_epi64(a, 32), v), round);
+    // (a * v + 2^56) >> 57, as the high halves shifted by 25
+    let t = _mm256_blend_epi32(_mm256_srli_epi64(even, 32), odd, 0b1010_1010);
+    let t = _mm256_srai_epi32(t, 25);
+    _mm256_sub_epi32(a, _mm256_mullo_epi32(t, _mm256_set1_epi32(Q)))
+}
+
+/// (-2q, 2q) to (-q, q)
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn fold(a: __m256i) -> __m256i {
+    let q = _mm256_set1_epi32(Q);
+    let a = _mm256_sub_epi32(
+        a,
+        _mm256_and_si256(q, _mm256_cmpgt_epi32(a, _mm256_set1_epi32(Q - 1))),
+    );
+    _mm256_add_epi32(
+        a,
+        _mm256_and_si256(q, _mm256_cmpgt_epi32(_mm256_set1_epi32(1 - Q), a)),
+    )
+}
+
+/// (-q, q) to [0, q)
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn caddq(a: __m256i) -> __m256i {
+    _mm256_add_epi32(
+        a,
+        _mm256_and_si256(_mm256_set1_epi32(Q), _mm256_srai_epi32(a, 31)),
+    )
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn load(a: &[i32], i: usize) -> __m256i {
+    _mm256_loadu_si256(a[i..i + 8].as_ptr() as *const __m256i)
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn store(a: &mut [i32], i: usize, v: __m256i) {
+    _mm256_storeu_si256(a[i..i + 8].as_mut_ptr() as *mut __m256i, v)
+}
+
+/// the butterfly of `ntt::butterfly` on 8 lanes; inputs and outputs are in (-q, q)
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn butterfly(x: __m256i, y: __m256i, w: __m256i) -> (__m256i, __m256i) {
+    (
+        fold(_mm256_add_epi32(x, y)),
+        montgomery_mul(_mm256_sub_epi32(x, y), w),
+    )
+}
+
+/// the 8 layers of butterflies of both transforms
+#[target_feature(enable = "avx2")]
+unsafe fn layers(a: &mut [i32; 256], roots: &[i32; 256]) {
+    // layers 1 to 5: the two inputs of a butterfly are in different vectors
+    for i in 1..=5 {
+        let m = 1 << (8 - i);
+        let offset = 256 - 2 * m;
+        for j in 0..(1 << (i - 1)) {
+            let t = 2 * j * m;
+            for k in (0..m).step_by(8) {
+                let w = load(roots, offset + k);
+                let (x, y) = butterfly(load(a, t + k), load(a, t + k + m), w);
+                store(a, t + k, x);
+                store(a, t + k + m, y);
+            }
+        }
+    }
+
+    // layers 6 to 8 work within 8 coefficients; we process 16 at a time
+    // and shuffle the inputs of the butterflies into separate vectors
+    let w6 = _mm256_setr_epi32(
+        roots[248], roots[249], roots[250], roots[251], roots[248], roots[249], roots[250],
+        roots[251],
+    );
+    let w7 = _mm256_setr_epi32(
+        roots[252], roots[253], roots[252], roots[253], roots[252], roots[253], roots[252],
+        roots[253],
+    );
+    let w8 = _mm256_set1_epi32(roots[254]);
+    for t in (0..256).step_by(16) {
+        let v0 = load(a, t);
+        let v1 = load(a, t + 8);
+
+        // m = 4: the 128 bit halves
+        let x = _mm256_permute2x128_si256(v0, v1, 0x20);
+        let y = _mm256_permute2x128_si256(v0, v1, 0x31);
+        let (x, y) = butterfly(x, y, w6);
+        let v0 = _mm256_permute2x128_si256(x, y, 0x20);
+        let v1 = _mm256_permute2x128_si256(x, y, 0x31);
+
+        // m = 2: the 64 bit pairs
+        let x = _mm256_unpacklo_epi64(v0, v1);
+        let y = _mm256_unpackhi_epi64(v0, v1);
+        let (x, y) = butterfly(x, y, w7);
+        let v0 = _mm256_unpacklo_epi64(x, y);
+        let v1 = _mm256_unpackhi_epi64(x, y);
+
+        // m = 1: the even and odd lanes
+        let x = _mm256_blend_epi32(v0, _mm256_slli_epi64(v1, 32), 0b1010_1010);
+        let y = _mm256_blend_epi32(_mm256_srli_epi64(v0, 32), v1, 0b1010_1010);
+        let (x, y) = butterfly(x, y, w8);
+        let v0 = _mm256_blend_epi32(x, _mm256_slli_epi64(y, 32), 0b1010_1010);
+        let v1 = _mm256_blend_epi32(_mm256_srli_epi64(x, 32), y, 0b1010_1010);
+
+        store(a, t, v0);
+        store(a, t + 8, v1);
+    }
+}
+
+/// see `ntt::forward_ntt`
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn forward_ntt(a: &mut [i32; 256]) {
+    for i in (0..256).step_by(8) {
+        let x = montgomery_mul(load(a, i), load(&ROOTS_MONT, i));
+        store(a, i, x);
+    }
+    layers(a, &FWD_LAYER_ROOTS);
+    for i in (0..256).step_by(8) {
+        let x = caddq(load(a, i));
+        store(a, i, x);
+    }
+}
+
+/// see `ntt::reverse_ntt`
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn reverse_ntt(a: &mut [i32; 256]) {
+    for i in (0..256).step_by(8) {
+        let x = barrett_reduce(load(a, i));
+        store(a, i, x);
+    }
+    rearrange(a);
+    layers(a, &INV_LAYER_ROOTS);
+    rearrange(a);
+    for i in (0..256).step_by(8) {
+        let x = caddq(montgomery_mul(load(a, i), load(&UNTWIST_MONT, i)));
+        store(a, i, x);
+    }
+}
+
+/// canonical a + b
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn add(a: &Poly256, b: &Poly256, res: &mut Poly256) {
+    for i in (0..256).step_by(8) {
+        let x = barrett_reduce(load(&a.coeff, i));
+        let y = barrett_reduce(load(&b.coeff, i));
+        store(&mut res.coeff, i, caddq(fold(_mm256_add_epi32(x, y))));
+    }
+}
+
+/// canonical a - b
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn sub(a: &Poly256, b: &Poly256, res: &mut Poly256) {
+    for i in (0..256).step_by(8) {
+        let x = barrett_reduce(load(&a.coeff, i));
+        let y = barrett_reduce(load(&b.coeff, i));
+        store(&mut res.coeff, i, caddq(fold(_mm256_sub_epi32(x, y))));
+    }
+}
+
+/// canonical coefficient-wise product
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn pointwise_mul(a: &Poly256, b: &Poly256, res: &mut Poly256) {
+    let r2 = _mm256_set1_epi32(Q_MONT2);
+    for i in (0..256).step_by(8) {
+        let x = barrett_reduce(load(&a.coeff, i));
+        let prod = montgomery_mul(x, load(&b.coeff, i));
+        // multiply by 2^64 to undo the two Montgomery reductions
+        store(&mut res.coeff, i, caddq(montgomery_mul(prod, r2)));
+    }
+}
+
+/// canonical inner product in the NTT domain
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn inner_product(a: &[Poly256], b: &[Poly256], res: &mut Poly256) {
+    let r2 = _mm256_set1_epi32(Q_MONT2);
+    for i in (0..256).step_by(8) {
+        let mut acc = _mm256_setzero_si256();
+        for (x, y) in a.iter().zip(b.iter()) {
+            let x = barrett_reduce(load(&x.coeff, i));
+            let prod = montgomery_mul(x, load(&y.coeff, i));
+            acc = fold(_mm256_add_epi32(acc, prod));
+        }
+        store(&mut res.coeff, i, caddq(montgomery_mul(acc, r2)));
+    }
+}
+
+/// whether all coefficients are within [-bound, bound]
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn check_norm(z: &[Poly256], bound: i32) -> bool {
+    let b = _mm256_set1_epi32(bound);
+    let mut acc = _mm256_setzero_si256();
+    for e in z.iter() {
+        for i in (0..256).step_by(8) {
+            let x = load(&e.coeff, i);
+            let out = _mm256_or_si256(_mm256_sub_epi32(b, x), _mm256_add_epi32(x, b));
+            acc = _mm256_or_si256(acc, out);
+        }
+    }
+    _mm256_movemask_ps(_mm256_castsi256_ps(acc)) == 0
+}
@@ -0,0 +1,50 @@
+// this file implements constant time helpers for the arithmetics over
+// secret dependent values
+//
+// none of the functions below branch on, or index memory with, their inputs;
+// the only instructions used are additions, multiplications, shifts and
+// bitwise operations which run in constant time on the platforms we target.
+// the modular reductions are in `reduce.rs`.
+
+/// lift x from [0, m) to (-m/2, m/2] without branching
+#[inline(always)]
+pub(crate) fn center(x: i32, m: i32) -> i32 {
+    x - (m & ((m - (x << 1)) >> 31))
+}
+
+/// returns 1 if x is out of [-bound, bound], 0 otherwise, without branching
+#[inline(always)]
+pub(crate) fn out_of_bound(x: i32, bound: i32) -> i32 {
+    ((bound.wrapping_sub(x) | x.wrapping_add(bound)) >> 31) & 1
+}
+
+/// returns true if a and b are equal; the time only depends on the lengths
+pub(crate) fn eq<T: Copy + Into<i64>>(a: &[T], b: &[T]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut diff = 0i64;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= (*x).into() ^ (*y).into();
+    }
+    diff == 0
+}
+
+#[test]
+fn test_ct_helpers() {
+    use crate::param::{P, Q};
+    for m in [P, Q].iter() {
+        for r in [0, 1, *m >> 1, (*m >> 1) + 1, *m - 1].iter() {
+            let c = center(*r, *m);
+            assert_eq!(c, if r << 1 > *m { r - *m } else { *r });
+        }
+    }
+    for x in -5..=5 {
+        assert_eq!(out_of_bound(x, 3), !(-3..=3).contains(&x) as i32);
+    }
+    assert_eq!(out_of_bound(i32::MAX, 3), 1);
+    assert_eq!(out_of_bound(i32::MIN, 3), 1);
+    assert!(eq(&[1, -2, 3], &[1, -2, 3]));
+    assert!(!eq(&[1, -2, 3], &[1, 2, 3]));
+    assert!(!eq(&[1u8, 2], &[1u8, 2, 3]));
+}
@@ -1,2 +1,578 @@
-/// this module is a wrapper of libsodium-ecvrf APIs
-extern crate libc;
+// this file implements ECVRF-EDWARDS25519-SHA512-TAI and
+// ECVRF-EDWARDS25519-SHA512-ELL2 as specified in RFC 9381
+//
+// proofs are 80 bytes: the encoding of Gamma, 16 bytes of challenge,
+// and 32 bytes of response; they are byte compatible with other
+// implementations of the RFC.
+//
+// note: hash to curve and verification work on public data only and
+// are not constant time.
+
+use crate::error::{Error, Result};
+use crate::keypair::{ECPublicKey, ECSecretKey};
+use crate::VRF;
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use sha2::{Digest, Sha512};
+use zeroize::{Zeroize, Zeroizing};
+
+/// length of a proof in bytes
+pub const EC_PROOF_LEN: usize = 80;
+
+// domain separator of hash to curve for the ELL2 suite
+const ELL2_H2C_SUITE: &[u8] = b"edwards25519_XMD:SHA-512_ELL2_NU_";
+
+/// the cipher suites of RFC 9381 over edwards25519
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ECVRFSuite {
+    /// ECVRF-EDWARDS25519-SHA512-TAI, hash to curve with try and increment
+    Tai,
+    /// ECVRF-EDWARDS25519-SHA512-ELL2, hash to curve with Elligator2
+    Ell2,
+}
+
+impl ECVRFSuite {
+    /// the suite_string of RFC 9381
+    pub fn suite_string(&self) -> u8 {
+        match self {
+            ECVRFSuite::Tai => 0x03,
+            ECVRFSuite::Ell2 => 0x04,
+        }
+    }
+
+    pub(crate) fn from_suite_string(suite: u8) -> Option<Self> {
+        match suite {
+            0x03 => Some(ECVRFSuite::Tai),
+            0x04 => Some(ECVRFSuite::Ell2),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct ECProof {
+    pub(crate) gamma: EdwardsPoint,
+    pub(crate) c: [u8; 16],
+    pub(crate) s: Scalar,
+}
+
+/// the output of the ECVRF is the 64 bytes beta_string
+pub type ECVRFOutput = [u8; 64];
+
+impl ECProof {
+    /// the vrf output carried by the proof under the given suite;
+    /// this does not verify the proof
+    pub fn proof_to_hash(&self, suite: ECVRFSuite) -> ECVRFOutput {
+        let mut hasher = Sha512::new();
+        hasher.update([suite.suite_string(), 0x03]);
+        hasher.update(self.gamma.mul_by_cofactor().compress().as_bytes());
+        hasher.update([0x00]);
+        let digest = hasher.finalize();
+        let mut res = [0u8; 64];
+        res.copy_from_slice(&digest);
+        res
+    }
+
+    /// the 80 bytes pi_string of RFC 9381
+    pub fn to_bytes(&self) -> [u8; EC_PROOF_LEN] {
+        let mut res = [0u8; EC_PROOF_LEN];
+        res[0..32].copy_from_slice(self.gamma.compress().as_bytes());
+        res[32..48].copy_from_slice(&self.c);
+        res[48..80].copy_from_slice(self.s.as_bytes());
+        res
+    }
+
+    /// parse a pi_string; returns an error if Gamma is not a valid
+    /// point or s is not reduced
+    pub fn from_bytes(bytes: &[u8; EC_PROOF_LEN]) -> Result<Self> {
+        let gamma = match string_to_point(&bytes[0..32]) {
+            Some(p) => p,
+            None => return Err(Error::MalformedEncoding("invalid point")),
+        };
+        let mut c = [0u8; 16];
+        c.copy_from_slice(&bytes[32..48]);
+        let mut s = [0u8; 32];
+        s.copy_from_slice(&bytes[48..80]);
+        let s = match Option::<Scalar>::from(Scalar::from_canonical_bytes(s)) {
+            Some(p) => p,
+            None => return Err(Error::MalformedEncoding("non-canonical scalar")),
+        };
+        Ok(Self { gamma, c, s })
+    }
+}
+
+pub struct ECVRF;
+
+impl VRF for ECVRF {
+    type PubParam = ECVRFSuite;
+    type PublicKey = ECPublicKey;
+    type SecretKey = ECSecretKey;
+    type Proof = ECProof;
+    type VrfOutput = ECVRFOutput;
+
+    /// ECVRF has no public parameters other than the suite;
+    /// the seed is not used and the TAI suite is returned
+    fn paramgen(_seed: [u8; 32]) -> Result<Self::PubParam> {
+        Ok(ECVRFSuite::Tai)
+    }
+
+    /// input a seed and a parameter output a pair of keys;
+    /// the seed is used as the RFC 8032 secret key
+    fn keygen(seed: [u8; 32], _pp: &Self::PubParam) -> Result<(Self::PublicKey, Self::SecretKey)> {
+        let sk = ECSecretKey { seed };
+        let pk = ECPublicKey {
+            point: &*secret_scalar(&sk) * ED25519_BASEPOINT_TABLE,
+        };
+        Ok((pk, sk))
+    }
+
+    /// input a message, a public parameter, a pair of keys
+    /// generate a vrf proof; the nonce is derived from the secret key
+    /// as in RFC 8032, so the seed is not used
+    fn prove<Blob: AsRef<[u8]>>(
+        message: Blob,
+        pp: &Self::PubParam,
+        pk: &Self::PublicKey,
+        sk: &Self::SecretKey,
+        _seed: [u8; 32],
+    ) -> Result<Self::Proof> {
+        let x = secret_scalar(sk);
+        if &*x * ED25519_BASEPOINT_TABLE != pk.point {
+            return Err(Error::ParamMismatch);
+        }
+        let pk_string = pk.point.compress();
+
+        // step 1: H = ECVRF_encode_to_curve(Y, alpha)
+        let h = match encode_to_curve(*pp, pk_string.as_bytes(), message.as_ref()) {
+            Some(p) => p,
+            None => return Err(Error::EntropyExhausted),
+        };
+        let h_string = h.compress();
+
+        // step 2: Gamma = x*H
+        let gamma = *x * h;
+
+        // step 3: k = ECVRF_nonce_generation(SK, h_string)
+        let mut hasher = Sha512::new();
+        hasher.update(sk.seed);
+        let mut hashed_sk = hasher.finalize();
+        let mut hasher = Sha512::new();
+        hasher.update(&hashed_sk[32..64]);
+        hashed_sk[..].zeroize();
+        hasher.update(h_string.as_bytes());
+        let mut digest = hasher.finalize();
+        let mut k_string = Zeroizing::new([0u8; 64]);
+        k_string.copy_from_slice(&digest);
+        digest[..].zeroize();
+        let k = Zeroizing::new(Scalar::from_bytes_mod_order_wide(&k_string));
+
+        // step 4: c = ECVRF_challenge_generation(Y, H, Gamma, k*B, k*H)
+        let c = challenge(
+            *pp,
+            &[
+                pk_string,
+                h_string,
+                gamma.compress(),
+                (&*k * ED25519_BASEPOINT_TABLE).compress(),
+                (*k * h).compress(),
+            ],
+        );
+
+        // step 5: s = (k + c*x) mod q
+        let s = *k + challenge_scalar(&c) * *x;
+        Ok(ECProof { gamma, c, s })
+    }
+
+    /// input a message, a public parameter, the public key, and a proof
+    /// generate the beta_string if proof is valid
+    fn verify<Blob: AsRef<[u8]>>(
+        message: Blob,
+        pp: &Self::PubParam,
+        pk: &Self::PublicKey,
+        proof: &Self::Proof,
+    ) -> Result<Option<Self::VrfOutput>> {
+        // step 1: ECVRF_validate_key
+        if pk.point.is_small_order() {
+            return Ok(None);
+        }
+        let pk_string = pk.point.compress();
+
+        // step 2: H = ECVRF_encode_to_curve(Y, alpha)
+        let h = match encode_to_curve(*pp, pk_string.as_bytes(), message.as_ref()) {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+
+        // step 3: U = s*B - c*Y, V = s*H - c*Gamma
+        let c = challenge_scalar(&proof.c);
+        let u = EdwardsPoint::vartime_double_scalar_mul_basepoint(&(-c), &pk.point, &proof.s);
+        let v = proof.s * h - c * proof.gamma;
+
+        // step 4: recompute the challenge
+        let c_prime = challenge(
+            *pp,
+            &[
+                pk_string,
+                h.compress(),
+                proof.gamma.compress(),
+                u.compress(),
+                v.compress(),
+            ],
+        );
+        if c_prime != proof.c {
+            return Ok(None);
+        }
+        Ok(Some(proof.proof_to_hash(*pp)))
+    }
+}
+
+/// the secret scalar x of RFC 8032, i.e., the clamped first half of SHA512(SK)
+fn secret_scalar(sk: &ECSecretKey) -> Zeroizing<Scalar> {
+    let mut hasher = Sha512::new();
+    hasher.update(sk.seed);
+    let mut digest = hasher.finalize();
+    let mut x = Zeroizing::new([0u8; 32]);
+    x.copy_from_slice(&digest[0..32]);
+    digest[..].zeroize();
+    x[0] &= 248;
+    x[31] &= 127;
+    x[31] |= 64;
+    Zeroizing::new(Scalar::from_bytes_mod_order(*x))
+}
+
+/// decode a point, rejecting non-canonical encodings as in RFC 8032
+pub(crate) fn string_to_point(bytes: &[u8]) -> Option<EdwardsPoint> {
+    let compressed = CompressedEdwardsY::from_slice(bytes).ok()?;
+    let point = compressed.decompress()?;
+    if point.compress() != compressed {
+        return None;
+    }
+    Some(point)
+}
+
+fn challenge(suite: ECVRFSuite, points: &[CompressedEdwardsY; 5]) -> [u8; 16] {
+    let mut hasher = Sha512::new();
+    hasher.update([suite.suite_string(), 0x02]);
+    for e in points.iter() {
+        hasher.update(e.as_bytes());
+    }
+    hasher.update([0x00]);
+    let digest = hasher.finalize();
+    let mut c = [0u8; 16];
+    c.copy_from_slice(&digest[0..16]);
+    c
+}
+
+fn challenge_scalar(c: &[u8; 16]) -> Scalar {
+    let mut buf = [0u8; 32];
+    buf[0..16].copy_from_slice(c);
+    Scalar::from_bytes_mod_order(buf)
+}
+
+/// ECVRF_encode_to_curve of RFC 9381; returns None if try and increment
+/// does not find a point, which happens with negligible probability
+pub(crate) fn encode_to_curve(
+    suite: ECVRFSuite,
+    pk_string: &[u8],
+    message: &[u8],
+) -> Option<EdwardsPoint> {
+    match suite {
+        ECVRFSuite::Tai => {
+            for ctr in 0..=255u8 {
+                let mut hasher = Sha512::new();
+                hasher.update([suite.suite_string(), 0x01]);
+                hasher.update(pk_string);
+                hasher.update(message);
+                hasher.update([ctr, 0x00]);
+                let digest = hasher.finalize();
+                if let Some(p) = string_to_point(&digest[0..32]) {
+                    return Some(p.mul_by_cofactor());
+                }
+            }
+            None
+        }
+        ECVRFSuite::Ell2 => {
+            let dst = [
+                b"ECVRF_".as_ref(),
+                ELL2_H2C_SUITE,
+                [suite.suite_string()].as_ref(),
+            ]
+            .concat();
+            Some(h2c_encode_to_curve(
+                &[pk_string, message].concat(),
+                dst.as_ref(),
+            ))
+        }
+    }
+}
+
+/// encode_to_curve of RFC 9380 for edwards25519_XMD:SHA-512_ELL2_NU_
+pub(crate) fn h2c_encode_to_curve(message: &[u8], dst: &[u8]) -> EdwardsPoint {
+    // hash_to_field with count = 1 and L = 48
+    let uniform_bytes = expand_message_xmd(message, dst, 48);
+    let u = Fe::from_be_bytes(&uniform_bytes);
+
+    // map_to_curve_elligator2 onto curve25519: t^2 = s^3 + J*s^2 + s
+    let j = Fe::from_u64(486_662);
+    let s_of = |x: &Fe| x.square().mul(x).add(&j.mul(&x.square())).add(x);
+    let mut x1 = j
+        .neg()
+        .mul(&Fe::one().add(&Fe::from_u64(2).mul(&u.square())).invert());
+    if x1.is_zero() {
+        x1 = j.neg();
+    }
+    let gx1 = s_of(&x1);
+    let (s, t) = match gx1.sqrt() {
+        Some(y) => (x1, if y.sgn0() { y } else { y.neg() }),
+        None => {
+            let x2 = x1.neg().sub(&j);
+            // gx2 is a square whenever gx1 is not
+            let y = s_of(&x2).sqrt().unwrap_or_else(Fe::zero);
+            (x2, if y.sgn0() { y.neg() } else { y })
+        }
+    };
+
+    // rational map from curve25519 to edwards25519
+    let (x, y) = if t.is_zero() || s.add(&Fe::one()).is_zero() {
+        (Fe::zero(), Fe::one())
+    } else {
+        let c1 = Fe::from_u64(486_664).neg().sqrt().unwrap_or_else(Fe::zero);
+        let c1 = if c1.sgn0() { c1.neg() } else { c1 };
+        (
+            c1.mul(&s).mul(&t.invert()),
+            s.sub(&Fe::one()).mul(&s.add(&Fe::one()).invert()),
+        )
+    };
+    let mut bytes = y.to_bytes();
+    bytes[31] |= (x.sgn0() as u8) << 7;
+    let point = CompressedEdwardsY(bytes)
+        .decompress()
+        .expect("the rational map always lands on edwards25519");
+    point.mul_by_cofactor()
+}
+
+/// expand_message_xmd of RFC 9380 with SHA-512
+pub(crate) fn expand_message_xmd(message: &[u8], dst: &[u8], len: usize) -> Vec<u8> {
+    let ell = len.div_ceil(64);
+    let dst_prime = [dst, [dst.len() as u8].as_ref()].concat();
+
+    let mut hasher = Sha512::new();
+    hasher.update([0u8; 128]);
+    hasher.update(message);
+    hasher.update((len as u16).to_be_bytes());
+    hasher.update([0u8]);
+    hasher.update(&dst_prime);
+    let b0 = hasher.finalize();
+
+    let mut res: Vec<u8> = Vec::with_capacity(ell * 64);
+    let mut bi = [0u8; 64];
+    for i in 1..=ell {
+        let mut hasher = Sha512::new();
+        if i == 1 {
+            hasher.update(b0);
+        } else {
+            let mut tmp = [0u8; 64];
+            for (e, (f, g)) in tmp.iter_mut().zip(b0.iter().zip(bi.iter())) {
+                *e = f ^ g;
+            }
+            hasher.update(tmp);
+        }
+        hasher.update([i as u8]);
+        hasher.update(&dst_prime);
+        bi.copy_from_slice(&hasher.finalize());
+        res.extend_from_slice(&bi);
+    }
+    res.truncate(len);
+    res
+}
+
+// p = 2^255 - 19, little endian limbs
+const FE_P: [u64; 4] = [
+    0xffff_ffff_ffff_ffed,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x7fff_ffff_ffff_ffff,
+];
+// p - 2
+const FE_P_M2: [u64; 4] = [
+    0xffff_ffff_ffff_ffeb,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x7fff_ffff_ffff_ffff,
+];
+// (p + 3) / 8
+const FE_P_P3_D8: [u64; 4] = [
+    0xffff_ffff_ffff_fffe,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x0fff_ffff_ffff_ffff,
+];
+// (p - 1) / 4
+const FE_P_M1_D4: [u64; 4] = [
+    0xffff_ffff_ffff_fffb,
+    0xffff_ffff_ffff_ffff,
+    0xffff_ffff_ffff_ffff,
+    0x1fff_ffff_ffff_ffff,
+];
+
+/// a minimal, variable time, field element mod 2^255 - 19 for hash to curve;
+/// the limbs are always fully reduced
+#[derive(PartialEq, Clone, Copy, Debug)]
+struct Fe([u64; 4]);
+
+impl Fe {
+    fn zero() -> Self {
+        Fe([0; 4])
+    }
+
+    fn one() -> Self {
+        Fe::from_u64(1)
+    }
+
+    fn from_u64(a: u64) -> Self {
+        Fe([a, 0, 0, 0])
+    }
+
+    // reduce a big endian integer of any length
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        let base = Fe::from_u64(256);
+        bytes.iter().fold(Fe::zero(), |acc, e| {
+            acc.mul(&base).add(&Fe::from_u64(*e as u64))
+        })
+    }
+
+    fn to_bytes(self) -> [u8; 32] {
+        let mut res = [0u8; 32];
+        for (e, f) in res.chunks_mut(8).zip(self.0.iter()) {
+            e.copy_from_slice(&f.to_le_bytes());
+        }
+        res
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    fn sgn0(&self) -> bool {
+        self.0[0] & 1 == 1
+    }
+
+    // subtract p while a >= p
+    fn reduce(mut a: [u64; 4]) -> Self {
+        while geq(&a, &FE_P) {
+            let mut borrow = false;
+            for (e, f) in a.iter_mut().zip(FE_P.iter()) {
+                let (r1, b1) = e.overflowing_sub(*f);
+                let (r2, b2) = r1.overflowing_sub(borrow as u64);
+                *e = r2;
+                borrow = b1 || b2;
+            }
+        }
+        Fe(a)
+    }
+
+    fn add(&self, b: &Self) -> Self {
+        // both inputs are below 2^255, so the sum does not overflow
+        let mut res = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, e) in res.iter_mut().enumerate() {
+            let tmp = self.0[i] as u128 + b.0[i] as u128 + carry;
+            *e = tmp as u64;
+            carry = tmp >> 64;
+        }
+        Fe::reduce(res)
+    }
+
+    fn neg(&self) -> Self {
+        if self.is_zero() {
+            return *self;
+        }
+        let mut res = [0u64; 4];
+        let mut borrow = false;
+        for (i, e) in res.iter_mut().enumerate() {
+            let (r1, b1) = FE_P[i].overflowing_sub(self.0[i]);
+            let (r2, b2) = r1.overflowing_sub(borrow as u64);
+            *e = r2;
+            borrow = b1 || b2;
+        }
+        Fe(res)
+    }
+
+    fn sub(&self, b: &Self) -> Self {
+        self.add(&b.neg())
+    }
+
+    fn mul(&self, b: &Self) -> Self {
+        let mut t = [0u128; 8];
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..4 {
+                let tmp = t[i + j] + self.0[i] as u128 * b.0[j] as u128 + carry;
+                t[i + j] = tmp & 0xffff_ffff_ffff_ffff;
+                carry = tmp >> 64;
+            }
+            t[i + 4] = carry;
+        }
+        // fold the high half with 2^256 = 38 mod p
+        let mut res = [0u64; 4];
+        let mut carry = 0u128;
+        for (i, e) in res.iter_mut().enumerate() {
+            let tmp = t[i] + t[i + 4] * 38 + carry;
+            *e = tmp as u64;
+            carry = tmp >> 64;
+        }
+        while carry != 0 {
+            let mut tmp_carry = carry * 38;
+            for e in res.iter_mut() {
+                let tmp = *e as u128 + tmp_carry;
+                *e = tmp as u64;
+                tmp_carry = tmp >> 64;
+            }
+            carry = tmp_carry;
+        }
+        Fe::reduce(res)
+    }
+
+    fn square(&self) -> Self {
+        self.mul(self)
+    }
+
+    fn pow(&self, e: &[u64; 4]) -> Self {
+        let mut res = Fe::one();
+        for i in (0..256).rev() {
+            res = res.square();
+            if (e[i / 64] >> (i % 64)) & 1 == 1 {
+                res = res.mul(self);
+            }
+        }
+        res
+    }
+
+    // inv0: returns 0 for 0
+    fn invert(&self) -> Self {
+        self.pow(&FE_P_M2)
+    }
+
+    // a square root, or None if self is not a square
+    fn sqrt(&self) -> Option<Self> {
+        let r = self.pow(&FE_P_P3_D8);
+        if r.square() == *self {
+            return Some(r);
+        }
+        let r = r.mul(&Fe::from_u64(2).pow(&FE_P_M1_D4));
+        if r.square() == *self {
+            return Some(r);
+        }
+        None
+    }
+}
+
+fn geq(a: &[u64; 4], b: &[u64; 4]) -> bool {
+    for i in (0..4).rev() {
+        if a[i] != b[i] {
+            return a[i] > b[i];
+        }
+    }
+    true
+}
@@ -0,0 +1,260 @@
+// a self-describing envelope around the `Serdes` encoding of a param, a key
+// or a proof, and its PEM-style armoring
+//
+// an envelope is
+//
+// * the magic bytes `LBVF`,
+// * the version of the envelope, `VERSION`,
+// * the type of the object, see `ObjectType`,
+// * the id of the parameter set, `ParameterSet::ID`,
+// * the 32 bytes `Param::digest` of the param the object belongs to; for a
+//   param, its own digest,
+// * the `Serdes` encoding of the object,
+// * a checksum: the first 4 bytes of SHA-512 of all the above.
+//
+// opening an envelope checks all of the above against the expected type, set
+// and param, so that an object is never decoded under another set or used
+// with another param.
+
+use crate::error::{Error, Result};
+use crate::keypair::{PublicKey, SecretKey};
+use crate::lbvrf::Proof;
+use crate::param::{Param, ParameterSet};
+use crate::serde::Serdes;
+use sha2::{Digest, Sha512};
+use zeroize::Zeroizing;
+
+/// magic bytes at the start of an envelope
+pub const MAGIC: [u8; 4] = *b"LBVF";
+
+/// version of the envelope written by this crate
+pub const VERSION: u8 = 1;
+
+/// number of bytes before the encoded object
+pub const HEADER_LEN: usize = MAGIC.len() + 3 + 32;
+
+/// number of bytes of the checksum
+pub const CHECKSUM_LEN: usize = 4;
+
+// number of base64 characters per line of an armored envelope
+const PEM_LINE_LEN: usize = 64;
+
+/// the type of the object in an envelope
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectType {
+    Param = 1,
+    PublicKey = 2,
+    SecretKey = 3,
+    Proof = 4,
+}
+
+impl ObjectType {
+    fn from_u8(e: u8) -> Result<Self> {
+        match e {
+            1 => Ok(ObjectType::Param),
+            2 => Ok(ObjectType::PublicKey),
+            3 => Ok(ObjectType::SecretKey),
+            4 => Ok(ObjectType::Proof),
+            _ => Err(Error::MalformedEncoding("unknown object type")),
+        }
+    }
+
+    /// the label of the armored object, e.g., `LB-VRF PUBLIC KEY`
+    pub fn label(self) -> &'static str {
+        match self {
+            ObjectType::Param => "LB-VRF PARAM",
+            ObjectType::PublicKey => "LB-VRF PUBLIC KEY",
+            ObjectType::SecretKey => "LB-VRF SECRET KEY",
+            ObjectType::Proof => "LB-VRF PROOF",
+        }
+    }
+}
+
+/// the objects that can be put in an envelope
+pub trait Envelope: Serdes + Sized {
+    /// type of the object
+    const OBJECT_TYPE: ObjectType;
+
+    /// id of the parameter set of the object
+    const SET_ID: u8;
+
+    /// the digest the object carries by itself, if any;
+    /// the envelope of the object must be bound to it
+    fn own_digest(&self) -> Option<[u8; 32]> {
+        None
+    }
+}
+
+impl<S: ParameterSet> Envelope for Param<S> {
+    const OBJECT_TYPE: ObjectType = ObjectType::Param;
+    const SET_ID: u8 = S::ID;
+
+    fn own_digest(&self) -> Option<[u8; 32]> {
+        Some(self.digest)
+    }
+}
+
+impl<S: ParameterSet> Envelope for PublicKey<S> {
+    const OBJECT_TYPE: ObjectType = ObjectType::PublicKey;
+    const SET_ID: u8 = S::ID;
+
+    fn own_digest(&self) -> Option<[u8; 32]> {
+        Some(self.digest)
+    }
+}
+
+impl<S: ParameterSet> Envelope for SecretKey<S> {
+    const OBJECT_TYPE: ObjectType = ObjectType::SecretKey;
+    const SET_ID: u8 = S::ID;
+}
+
+impl<S: ParameterSet> Envelope for Proof<S> {
+    const OBJECT_TYPE: ObjectType = ObjectType::Proof;
+    const SET_ID: u8 = S::ID;
+}
+
+/// the header of an envelope
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    pub object_type: ObjectType,
+    pub set_id: u8,
+    /// digest of the param the object belongs to
+    pub digest: [u8; 32],
+}
+
+impl Header {
+    /// parse the header of an envelope and check its magic bytes,
+    /// version and checksum; the object itself is not decoded
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_LEN + CHECKSUM_LEN {
+            return Err(Error::MalformedEncoding("truncated envelope"));
+        }
+        if bytes[0..4] != MAGIC {
+            return Err(Error::MalformedEncoding("not an envelope"));
+        }
+        if bytes[4] != VERSION {
+            return Err(Error::UnsupportedVersion(bytes[4]));
+        }
+        let (body, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        if checksum != &Sha512::digest(body)[0..CHECKSUM_LEN] {
+            return Err(Error::MalformedEncoding("checksum mismatch"));
+        }
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&bytes[7..HEADER_LEN]);
+        Ok(Self {
+            version: bytes[4],
+            object_type: ObjectType::from_u8(bytes[5])?,
+            set_id: bytes[6],
+            digest,
+        })
+    }
+}
+
+/// put an object in an envelope bound to the param of the given digest;
+/// for a param, this is its own digest
+///
+/// the envelope is wiped from memory when dropped, as it may hold a secret key
+pub fn seal<T: Envelope>(obj: &T, digest: &[u8; 32]) -> Result<Zeroizing<Vec<u8>>> {
+    if obj.own_digest().is_some_and(|e| &e != digest) {
+        return Err(Error::ParamMismatch);
+    }
+    let mut res = Zeroizing::new(Vec::with_capacity(HEADER_LEN + CHECKSUM_LEN));
+    res.extend_from_slice(&MAGIC);
+    res.extend_from_slice(&[VERSION, T::OBJECT_TYPE as u8, T::SET_ID]);
+    res.extend_from_slice(digest);
+    obj.serialize(&mut *res)?;
+    let checksum = Sha512::digest(&res[..]);
+    res.extend_from_slice(&checksum[0..CHECKSUM_LEN]);
+    Ok(res)
+}
+
+/// open an envelope bound to the param of the given digest
+///
+/// returns `Error::ParamMismatch` if the object belongs to another parameter
+/// set or another param, and `Error::MalformedEncoding` if the envelope holds
+/// another type of object or is corrupted
+pub fn open<T: Envelope>(bytes: &[u8], digest: &[u8; 32]) -> Result<T> {
+    let header = Header::parse(bytes)?;
+    if header.object_type != T::OBJECT_TYPE {
+        return Err(Error::MalformedEncoding("unexpected object type"));
+    }
+    if header.set_id != T::SET_ID || &header.digest != digest {
+        return Err(Error::ParamMismatch);
+    }
+    let mut reader = &bytes[HEADER_LEN..bytes.len() - CHECKSUM_LEN];
+    let res = T::deserialize(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(Error::MalformedEncoding("trailing bytes"));
+    }
+    // the digest of a param is recomputed by `Serdes`,
+    // and a public key carries its own
+    if res.own_digest().is_some_and(|e| &e != digest) {
+        return Err(Error::ParamMismatch);
+    }
+    Ok(res)
+}
+
+/// open the envelope of a param of any digest; the digest of the returned
+/// param should be checked against a trusted one before use
+pub fn open_param<S: ParameterSet>(bytes: &[u8]) -> Result<Param<S>> {
+    let header = Header::parse(bytes)?;
+    open(bytes, &header.digest)
+}
+
+/// armor an envelope:
+/// `-----BEGIN <label>-----`, the base64 envelope in lines of 64 characters,
+/// and `-----END <label>-----`, where the label depends on the object type
+pub fn armor(bytes: &[u8]) -> Result<Zeroizing<String>> {
+    let label = Header::parse(bytes)?.object_type.label();
+    let encoded = Zeroizing::new(base64::encode(bytes));
+    let mut res = Zeroizing::new(String::with_capacity(encoded.len() * 65 / 64 + 64));
+    res.push_str(&format!("-----BEGIN {}-----\n", label));
+    // base64 is ascii, so that the lines are valid strings
+    for line in encoded.as_bytes().chunks(PEM_LINE_LEN) {
+        res.push_str(std::str::from_utf8(line).expect("base64 is ascii"));
+        res.push('\n');
+    }
+    res.push_str(&format!("-----END {}-----\n", label));
+    Ok(res)
+}
+
+/// remove the armor of an envelope; the label must match
+/// the object type of the envelope
+pub fn dearmor(text: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let mut lines = text.trim().lines().map(|e| e.trim());
+    let label = lines
+        .next()
+        .and_then(|e| e.strip_prefix("-----BEGIN "))
+        .and_then(|e| e.strip_suffix("-----"))
+        .ok_or(Error::MalformedEncoding("missing armor header"))?;
+    let mut encoded = Zeroizing::new(String::with_capacity(text.len()));
+    let mut end = None;
+    for line in lines.by_ref() {
+        if let Some(p) = line.strip_prefix("-----END ") {
+            end = p.strip_suffix("-----");
+            break;
+        }
+        encoded.push_str(line);
+    }
+    if end != Some(label) || lines.next().is_some() {
+        return Err(Error::MalformedEncoding("missing armor footer"));
+    }
+    let bytes = Zeroizing::new(
+        base64::decode(&*encoded).map_err(|_| Error::MalformedEncoding("invalid base64"))?,
+    );
+    if Header::parse(&bytes)?.object_type.label() != label {
+        return Err(Error::MalformedEncoding("unexpected armor label"));
+    }
+    Ok(bytes)
+}
+
+/// seal and armor an object, see `seal`
+pub fn to_pem<T: Envelope>(obj: &T, digest: &[u8; 32]) -> Result<Zeroizing<String>> {
+    armor(&seal(obj, digest)?)
+}
+
+/// remove the armor and open an envelope, see `open`
+pub fn from_pem<T: Envelope>(text: &str, digest: &[u8; 32]) -> Result<T> {
+    open(&dearmor(text)?, digest)
+}
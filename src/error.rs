@@ -0,0 +1,61 @@
+use std::fmt;
+use std::io;
+
+/// errors returned by the public APIs of this crate
+#[derive(Debug)]
+pub enum Error {
+    /// the input bytes are not a valid encoding of the object
+    MalformedEncoding(&'static str),
+    /// a polynomial in the proof exceeds the norm bound
+    NormBound,
+    /// the challenge recomputed by the verifier does not match the proof
+    ChallengeMismatch,
+    /// hash to challenge used all the entropy of the hash output
+    EntropyExhausted,
+    /// the parameters, keys or proof do not belong together
+    ParamMismatch,
+    /// the envelope was written by an unknown version of this crate
+    UnsupportedVersion(u8),
+    /// the parameters are invalid
+    InvalidParam(&'static str),
+    /// all one time keys of a many time secret key have been used
+    KeyExhausted,
+    /// the one time key of the epoch has already been used
+    EpochUsed,
+    /// an I/O error from the underlying reader or writer
+    Io(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MalformedEncoding(e) => write!(f, "malformed encoding: {}", e),
+            Error::NormBound => write!(f, "norm bound violation"),
+            Error::ChallengeMismatch => write!(f, "challenge mismatch"),
+            Error::EntropyExhausted => write!(f, "hash to challenge used all entropy"),
+            Error::ParamMismatch => write!(f, "parameter mismatch"),
+            Error::UnsupportedVersion(e) => write!(f, "unsupported envelope version {}", e),
+            Error::InvalidParam(e) => write!(f, "invalid parameter: {}", e),
+            Error::KeyExhausted => write!(f, "all one time keys have been used"),
+            Error::EpochUsed => write!(f, "the one time key of the epoch has been used"),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
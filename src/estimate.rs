@@ -0,0 +1,240 @@
+// this file recomputes the constants of `param.rs` and estimates the security
+// of a parameter set; it replaces the sage scripts under `scripts/`
+//
+// the security estimate follows the core-SVP methodology: an attack is
+// costed by the block size b of the BKZ reduction it requires, and the cost
+// of BKZ-b is that of a single call to an SVP oracle in dimension b, i.e.,
+// 2^(0.292 b) classically and 2^(0.265 b) quantumly. two attacks are
+// considered:
+//
+// * key recovery, a module-LWE instance: writing A = [A1 | A2] with A2
+//   invertible, t = A * s gives A2^-1 * t = A2^-1 * A1 * s1 + s2, an LWE
+//   instance with a secret of dimension n * (L - K) and n * K samples whose
+//   errors are trinary; it is solved with the primal (uSVP) attack.
+// * forgery, a module-SIS instance: a short nonzero x with A * x = 0 mod q
+//   and |x|_inf <= 2 * BETA_M_KAPPA; the infinity norm bound B is relaxed to
+//   the euclidean bound B * sqrt(m), which underestimates the cost.
+
+use crate::param::{ParameterSet, P, Q, R};
+use std::f64::consts::{E, PI};
+use std::fmt;
+
+/// cost exponent of an SVP call in dimension b is CLASSICAL_SVP * b
+pub const CLASSICAL_SVP: f64 = 0.292;
+
+/// cost exponent of a quantum SVP call in dimension b is QUANTUM_SVP * b
+pub const QUANTUM_SVP: f64 = 0.265;
+
+/// the smallest block size that is considered; the root hermite factor
+/// formula is not meaningful below it
+const MIN_BLOCK_SIZE: usize = 50;
+
+/// the largest block size that is considered
+const MAX_BLOCK_SIZE: usize = 2000;
+
+/// the inputs of the estimator
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Setting {
+    /// degree of the ring Z_q[x]/(x^n+1)
+    pub degree: usize,
+    /// modulus of the `A part`
+    pub q: u32,
+    /// modulus of the `B part`
+    pub p: u32,
+    /// root s.t. (x^32+r) divides (x^n+1) mod p
+    pub r: u32,
+    /// number of rows of the matrix
+    pub k: usize,
+    /// number of columns of the matrix
+    pub l: usize,
+    /// number of non-zero coefficients in challenge
+    pub kappa: usize,
+    /// range for Y
+    pub beta: u32,
+}
+
+/// the outputs of the estimator
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    /// rejection sampling range for Q
+    pub q_rs_range: u32,
+    /// rejection sampling range for P
+    pub p_rs_range: u32,
+    /// rejection sampling range for 2 * beta + 1
+    pub beta_rs_range: u32,
+    /// [(-r)^0, ..., (-r)^(n/32-1)] mod p
+    pub r_base: Vec<i32>,
+    /// expected number of repetitions to generate a proof
+    pub repetitions: f64,
+    /// |c * s|_inf exceeds this bound with probability less than 2^-128
+    pub cs_tail: usize,
+    /// BKZ block size of the key recovery attack
+    pub mlwe_block_size: usize,
+    /// BKZ block size of the forgery attack
+    pub msis_block_size: usize,
+}
+
+impl Setting {
+    /// the setting of a parameter set
+    pub fn from_set<S: ParameterSet>() -> Self {
+        Self {
+            degree: 256,
+            q: Q as u32,
+            p: P as u32,
+            r: R as u32,
+            k: S::K,
+            l: S::L,
+            kappa: S::KAPPA,
+            beta: S::BETA as u32,
+        }
+    }
+
+    /// run the estimator
+    pub fn estimate(&self) -> Estimate {
+        let n = self.degree;
+        let bound = 2 * (self.beta - self.kappa as u32);
+        Estimate {
+            q_rs_range: rs_range(self.q),
+            p_rs_range: rs_range(self.p),
+            beta_rs_range: rs_range(2 * self.beta + 1),
+            r_base: r_base(self.p, self.r, n / 32),
+            repetitions: expected_repetitions(n * self.l, self.kappa as u32, self.beta),
+            cs_tail: cs_tail(self.kappa, n),
+            mlwe_block_size: mlwe_block_size(
+                n * (self.l - self.k),
+                n * self.k,
+                self.q,
+                (2.0f64 / 3.0).sqrt(),
+            ),
+            msis_block_size: msis_block_size(n * self.k, n * self.l, self.q, bound),
+        }
+    }
+}
+
+impl Estimate {
+    /// BKZ block size of the cheapest attack
+    pub fn block_size(&self) -> usize {
+        self.mlwe_block_size.min(self.msis_block_size)
+    }
+
+    /// classical core-SVP security in bits
+    pub fn classical_bits(&self) -> f64 {
+        CLASSICAL_SVP * self.block_size() as f64
+    }
+
+    /// quantum core-SVP security in bits
+    pub fn quantum_bits(&self) -> f64 {
+        QUANTUM_SVP * self.block_size() as f64
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "  q_rs_range:     {}", self.q_rs_range)?;
+        writeln!(f, "  p_rs_range:     {}", self.p_rs_range)?;
+        writeln!(f, "  beta_rs_range:  {}", self.beta_rs_range)?;
+        writeln!(f, "  r_base:         {:?}", self.r_base)?;
+        writeln!(f, "  repetitions:    {:.3}", self.repetitions)?;
+        writeln!(f, "  c*s tail:       {}", self.cs_tail)?;
+        writeln!(f, "  mlwe block:     {}", self.mlwe_block_size)?;
+        writeln!(f, "  msis block:     {}", self.msis_block_size)?;
+        writeln!(f, "  classical bits: {:.1}", self.classical_bits())?;
+        write!(f, "  quantum bits:   {:.1}", self.quantum_bits())
+    }
+}
+
+/// the largest multiple of modulus that fits in 32 bits;
+/// a random 32 bits integer smaller than it is uniform modulo modulus
+pub const fn rs_range(modulus: u32) -> u32 {
+    u32::MAX / modulus * modulus
+}
+
+/// [(-r)^0, (-r)^1, ..., (-r)^(len-1)] mod p
+pub fn r_base(p: u32, r: u32, len: usize) -> Vec<i32> {
+    let minus_r = (p - r % p) as u64;
+    let mut res = Vec::with_capacity(len);
+    let mut e = 1u64;
+    for _ in 0..len {
+        res.push(e as i32);
+        e = e * minus_r % p as u64;
+    }
+    res
+}
+
+/// expected number of repetitions to generate a proof with `len` coefficients
+/// in z: y is uniform within [-beta, beta] and the proof is accepted if
+/// |y + c * s|_inf <= beta - kappa
+pub fn expected_repetitions(len: usize, kappa: u32, beta: u32) -> f64 {
+    let accept = (2 * (beta - kappa) + 1) as f64 / (2 * beta + 1) as f64;
+    accept.powi(-(len as i32))
+}
+
+/// the smallest t s.t. the probability that |c * s|_inf > t is less than
+/// 2^-128, for a challenge c of weight kappa and a trinary s of the given
+/// degree; a coefficient of c * s is a sum of kappa uniform trinary values
+pub fn cs_tail(kappa: usize, degree: usize) -> usize {
+    // law[i] = Pr[coefficient = i - kappa]
+    let mut law = vec![1.0f64];
+    for _ in 0..kappa {
+        let mut next = vec![0.0; law.len() + 2];
+        for (i, e) in law.iter().enumerate() {
+            for f in next[i..i + 3].iter_mut() {
+                *f += e / 3.0;
+            }
+        }
+        law = next;
+    }
+
+    // union bound over the coefficients
+    let target = 2f64.powi(-128) / degree as f64;
+    let mut tail = 0.0;
+    for t in (0..kappa).rev() {
+        tail += law[kappa + t + 1] + law[kappa - t - 1];
+        if tail >= target {
+            return t + 1;
+        }
+    }
+    0
+}
+
+/// root hermite factor of BKZ with block size b
+pub fn root_hermite_factor(b: usize) -> f64 {
+    let b = b as f64;
+    ((PI * b).powf(1.0 / b) * b / (2.0 * PI * E)).powf(1.0 / (2.0 * (b - 1.0)))
+}
+
+/// smallest block size of the primal attack on LWE with a secret of dimension
+/// n, m samples modulo q and secret and errors of standard deviation sigma
+pub fn mlwe_block_size(n: usize, m: usize, q: u32, sigma: f64) -> usize {
+    let log_q = (q as f64).ln();
+    for b in MIN_BLOCK_SIZE..MAX_BLOCK_SIZE {
+        let log_delta = root_hermite_factor(b).ln();
+        let lhs = (sigma * (b as f64).sqrt()).ln();
+        // the attack may use any number of the samples
+        for samples in 0..=m {
+            let d = (n + samples + 1) as f64;
+            let rhs = (2.0 * b as f64 - d - 1.0) * log_delta + samples as f64 / d * log_q;
+            if lhs <= rhs {
+                return b;
+            }
+        }
+    }
+    MAX_BLOCK_SIZE
+}
+
+/// smallest block size to find a nonzero x with A * x = 0 mod q and
+/// |x|_inf <= bound, for an h * w matrix A
+pub fn msis_block_size(h: usize, w: usize, q: u32, bound: u32) -> usize {
+    let log_q = (q as f64).ln();
+    for b in MIN_BLOCK_SIZE..MAX_BLOCK_SIZE {
+        let log_delta = root_hermite_factor(b).ln();
+        // the attack may use any number of the columns
+        for m in h + 1..=w {
+            let len = (m as f64 * log_delta + h as f64 / m as f64 * log_q).min(log_q);
+            if len <= (bound as f64 * (m as f64).sqrt()).ln() {
+                return b;
+            }
+        }
+    }
+    MAX_BLOCK_SIZE
+}
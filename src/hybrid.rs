@@ -0,0 +1,148 @@
+// this file implements a hybrid VRF that combines the classical ECVRF with
+// the post-quantum LB-VRF
+//
+// both schemes evaluate the same message; the output is a hash of both
+// outputs, and a proof is only accepted if both component proofs are valid.
+// the output stays unpredictable as long as one of the two assumptions holds.
+//
+// note: the lattice component is a one time scheme, so a hybrid key pair
+// must only be used for a single message as well.
+
+use crate::ecvrf::{ECProof, ECVRFSuite, ECVRF};
+use crate::error::Result;
+use crate::keypair::{HybridPublicKey, HybridSecretKey};
+use crate::lbvrf::{output_to_hash, Proof, VRFOutput, LBVRF};
+use crate::param::Param;
+use crate::VRF;
+use sha2::{Digest, Sha512};
+use zeroize::{Zeroize, Zeroizing};
+
+/// public parameters of the hybrid scheme
+#[derive(Clone, Debug, PartialEq)]
+pub struct HybridParam {
+    pub ec: ECVRFSuite,
+    pub lb: Param,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct HybridProof {
+    pub(crate) ec: ECProof,
+    pub(crate) lb: Proof,
+}
+
+/// the output of the hybrid scheme is 64 bytes, see `combine_outputs`
+pub type HybridOutput = [u8; 64];
+
+impl HybridProof {
+    /// the classical component of the proof
+    pub fn ec_proof(&self) -> &ECProof {
+        &self.ec
+    }
+
+    /// the lattice component of the proof
+    pub fn lb_proof(&self) -> &Proof {
+        &self.lb
+    }
+
+    /// the combined output carried by the proof;
+    /// this does not verify the proof
+    pub fn proof_to_hash(&self, pp: &HybridParam) -> Result<HybridOutput> {
+        combine_outputs(&self.ec.proof_to_hash(pp.ec), &self.lb.output())
+    }
+}
+
+/// hash the outputs of both schemes into 64 uniformly random bytes
+pub fn combine_outputs(ec: &[u8; 64], lb: &VRFOutput) -> Result<HybridOutput> {
+    let mut hasher = Sha512::new();
+    hasher.update("domain seperator: hybrid output");
+    hasher.update(&ec[..]);
+    hasher.update(&output_to_hash(lb)?[..]);
+    let digest = hasher.finalize();
+    let mut res = [0u8; 64];
+    res.copy_from_slice(&digest);
+    Ok(res)
+}
+
+pub struct HybridVRF;
+
+impl VRF for HybridVRF {
+    type PubParam = HybridParam;
+    type PublicKey = HybridPublicKey;
+    type SecretKey = HybridSecretKey;
+    type Proof = HybridProof;
+    type VrfOutput = HybridOutput;
+
+    /// input some seed, generate public parameters
+    /// the lattice matrix is expanded from the seed, and the ECVRF
+    /// uses the TAI suite
+    fn paramgen(seed: [u8; 32]) -> Result<Self::PubParam> {
+        Ok(HybridParam {
+            ec: ECVRF::paramgen(seed)?,
+            lb: LBVRF::paramgen(seed)?,
+        })
+    }
+
+    /// input a seed and a parameter output a pair of keys
+    /// the seeds of the component keys are derived from the seed
+    fn keygen(seed: [u8; 32], pp: &Self::PubParam) -> Result<(Self::PublicKey, Self::SecretKey)> {
+        let ec_seed = derive_seed(&seed, "ec key");
+        let lb_seed = derive_seed(&seed, "lb key");
+        let (ec_pk, ec_sk) = ECVRF::keygen(*ec_seed, &pp.ec)?;
+        let (lb_pk, lb_sk) = LBVRF::keygen(*lb_seed, &pp.lb)?;
+        Ok((
+            HybridPublicKey {
+                ec: ec_pk,
+                lb: lb_pk,
+            },
+            HybridSecretKey {
+                ec: ec_sk,
+                lb: lb_sk,
+            },
+        ))
+    }
+
+    /// input a message, a public parameter, a pair of keys
+    /// generate a proof for each of the schemes
+    fn prove<Blob: AsRef<[u8]>>(
+        message: Blob,
+        pp: &Self::PubParam,
+        pk: &Self::PublicKey,
+        sk: &Self::SecretKey,
+        seed: [u8; 32],
+    ) -> Result<Self::Proof> {
+        let ec = ECVRF::prove(message.as_ref(), &pp.ec, &pk.ec, &sk.ec, seed)?;
+        let lb = LBVRF::prove(message.as_ref(), &pp.lb, &pk.lb, &sk.lb, seed)?;
+        Ok(HybridProof { ec, lb })
+    }
+
+    /// input a message, a public parameter, the public key, and a proof
+    /// generate the combined output if both proofs are valid
+    fn verify<Blob: AsRef<[u8]>>(
+        message: Blob,
+        pp: &Self::PubParam,
+        pk: &Self::PublicKey,
+        proof: &Self::Proof,
+    ) -> Result<Option<Self::VrfOutput>> {
+        let ec = match ECVRF::verify(message.as_ref(), &pp.ec, &pk.ec, &proof.ec)? {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        let lb = match LBVRF::verify(message.as_ref(), &pp.lb, &pk.lb, &proof.lb)? {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        Ok(Some(combine_outputs(&ec, &lb)?))
+    }
+}
+
+fn derive_seed(seed: &[u8; 32], label: &str) -> Zeroizing<[u8; 32]> {
+    let mut hasher = Sha512::new();
+    hasher.update(seed);
+    hasher.update("domain seperator: hybrid ");
+    hasher.update(label);
+    let mut digest = hasher.finalize();
+    let mut res = Zeroizing::new([0u8; 32]);
+    res.copy_from_slice(&digest[0..32]);
+    digest[..].zeroize();
+    res
+}
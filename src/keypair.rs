@@ -1,12 +1,206 @@
 // use crate::poly::PolyArith;
+use crate::ct;
+use crate::error::{Error, Result};
+use crate::param::{ParameterSet, Standard};
 use crate::poly256::Poly256;
+use crate::serde::Serdes;
+use curve25519_dalek::edwards::EdwardsPoint;
+use sha2::{Digest, Sha512};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
+/// number of bytes of a public key fingerprint
+pub const FINGERPRINT_LEN: usize = 16;
+
+/// public key of the one time scheme: t = A s, of length K, and the digest
+/// of the param A; the key is rejected with `Error::ParamMismatch` when it is
+/// used with another param
+#[derive(PartialEq, Clone, Debug)]
+pub struct PublicKey<S: ParameterSet = Standard> {
+    pub(crate) t: Vec<Poly256>,
+    pub(crate) digest: [u8; 32],
+    pub(crate) set: PhantomData<S>,
+}
+
+impl<S: ParameterSet> PublicKey<S> {
+    /// digest of the param the key is generated under, see `Param::digest`
+    pub fn param_digest(&self) -> [u8; 32] {
+        self.digest
+    }
+
+    /// a short hash of the key and its parameter set, for display and
+    /// indexing; keys of different params have different fingerprints
+    pub fn fingerprint(&self) -> Result<[u8; FINGERPRINT_LEN]> {
+        let mut buf: Vec<u8> = vec![];
+        self.serialize(&mut buf)?;
+        let mut hasher = Sha512::new();
+        hasher.update("domain seperator: public key fingerprint");
+        hasher.update([S::ID]);
+        hasher.update(buf);
+        let digest = hasher.finalize();
+        let mut res = [0u8; FINGERPRINT_LEN];
+        res.copy_from_slice(&digest[0..FINGERPRINT_LEN]);
+        Ok(res)
+    }
+}
+
+/// secret key of the one time scheme: a trinary s of length L;
+/// the secret key is wiped from memory when it is dropped
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey<S: ParameterSet = Standard> {
+    pub(crate) s: Vec<Poly256>,
+    #[zeroize(skip)]
+    pub(crate) set: PhantomData<S>,
+}
+
+/// public key of the many time scheme: the root of a Merkle tree
+/// whose leaves are one time public keys
 #[derive(PartialEq, Clone, Copy, Debug)]
-pub struct PublicKey {
-    pub(crate) t: [Poly256; 4],
+pub struct MTPublicKey {
+    pub(crate) root: [u8; 32],
+}
+
+/// secret key of the many time scheme
+///
+/// the one time secret keys are re-derived from `seed`; the leaves are used
+/// in increasing order of epochs and `next_leaf` is the smallest epoch that
+/// can still be used. The counter is shared between clones of the same key
+/// so that a leaf is never handed out twice. Callers must persist the
+/// serialized key after each proof to keep the state across restarts.
+///
+/// the seed is wiped from memory when the key is dropped
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct MTSecretKey {
+    pub(crate) seed: [u8; 32],
+    #[zeroize(skip)]
+    pub(crate) height: usize,
+    #[zeroize(skip)]
+    pub(crate) next_leaf: Arc<AtomicUsize>,
+    // Merkle tree nodes in heap order: nodes[1] is the root,
+    // leaves are stored at nodes[2^height..2^(height+1)];
+    // this is a cache that is rebuilt from the seed when missing
+    #[zeroize(skip)]
+    pub(crate) nodes: Arc<OnceLock<Vec<[u8; 32]>>>,
 }
 
+impl MTSecretKey {
+    /// index of the next leaf that will be used
+    pub fn next_leaf(&self) -> usize {
+        self.next_leaf.load(Ordering::SeqCst)
+    }
+
+    /// number of proofs that can still be generated with this key
+    pub fn remaining(&self) -> usize {
+        (1 << self.height) - self.next_leaf().min(1 << self.height)
+    }
+
+    /// reserve the leaf of the given epoch; the leaves of the skipped
+    /// epochs are never used
+    pub(crate) fn reserve_leaf(&self, epoch: u64) -> Result<usize> {
+        let leaves = 1u64 << self.height;
+        if epoch >= leaves {
+            return Err(Error::KeyExhausted);
+        }
+        let leaf = epoch as usize;
+        self.next_leaf
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| {
+                if x <= leaf {
+                    Some(leaf + 1)
+                } else {
+                    None
+                }
+            })
+            .map(|_| leaf)
+            .map_err(|_| Error::EpochUsed)
+    }
+}
+
+impl PartialEq for MTSecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        ct::eq(&self.seed, &other.seed)
+            & (self.height == other.height)
+            & (self.next_leaf() == other.next_leaf())
+    }
+}
+
+/// public key of the ECVRF: Y = x*B
 #[derive(PartialEq, Clone, Copy, Debug)]
-pub struct SecretKey {
-    pub(crate) s: [Poly256; 9],
+pub struct ECPublicKey {
+    pub(crate) point: EdwardsPoint,
+}
+
+/// secret key of the ECVRF: the 32 bytes RFC 8032 secret key;
+/// it is wiped from memory when it is dropped
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct ECSecretKey {
+    pub(crate) seed: [u8; 32],
+}
+
+/// public key of the hybrid scheme: one key for each component
+#[derive(PartialEq, Clone, Debug)]
+pub struct HybridPublicKey {
+    pub(crate) ec: ECPublicKey,
+    pub(crate) lb: PublicKey,
+}
+
+/// secret key of the hybrid scheme: one key for each component
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct HybridSecretKey {
+    pub(crate) ec: ECSecretKey,
+    pub(crate) lb: SecretKey,
+}
+
+// the secret keys are compared in constant time, and never printed
+
+impl<S: ParameterSet> PartialEq for SecretKey<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.s.len() == other.s.len()
+            && self
+                .s
+                .iter()
+                .zip(other.s.iter())
+                .fold(true, |acc, (x, y)| acc & ct::eq(&x.coeff, &y.coeff))
+    }
+}
+
+impl PartialEq for ECSecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        ct::eq(&self.seed, &other.seed)
+    }
+}
+
+impl PartialEq for HybridSecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        (self.ec == other.ec) & (self.lb == other.lb)
+    }
+}
+
+impl<S: ParameterSet> fmt::Debug for SecretKey<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SecretKey").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for MTSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MTSecretKey")
+            .field("height", &self.height)
+            .field("next_leaf", &self.next_leaf())
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for ECSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ECSecretKey").finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for HybridSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HybridSecretKey").finish_non_exhaustive()
+    }
 }
@@ -1,71 +1,105 @@
 #![allow(clippy::many_single_char_names)]
-// use crate::keypair::PublicKey;
+#[cfg(target_arch = "x86_64")]
+use crate::avx2;
+use crate::ct;
+use crate::keypair::{PublicKey, SecretKey};
+use crate::parallel;
 use crate::param::*;
-use crate::poly::PolyArith;
+use crate::poly::{PolyArith, ShortSample, TrinaryMul, UniformSample};
 use crate::poly256::*;
 use crate::poly32::poly32_inner_product;
 use crate::poly32::Poly32;
 // use rand::{CryptoRng, RngCore};
 // use crate::Poly32::*;
+use crate::error::{Error, Result};
 use crate::serde::Serdes;
 use crate::VRF;
-use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use rand_chacha::rand_core::{CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use sha2::{Digest, Sha512};
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::marker::PhantomData;
+use std::sync::atomic::{compiler_fence, Ordering};
+use zeroize::Zeroizing;
 
-#[derive(PartialEq, Clone, Copy, Debug)]
-pub struct Proof {
-    pub(crate) z: [Poly256; 9],
+#[derive(PartialEq, Clone, Debug)]
+pub struct Proof<S: ParameterSet = Standard> {
+    pub(crate) z: Vec<Poly256>,
     pub(crate) c: Poly256,
     pub(crate) v: VRFOutput,
+    pub(crate) set: PhantomData<S>,
 }
 
 pub type VRFOutput = Poly32;
 
-pub struct LBVRF;
+impl<S: ParameterSet> Proof<S> {
+    /// the vrf output carried by the proof;
+    /// this does not verify the proof
+    pub fn output(&self) -> VRFOutput {
+        self.v
+    }
+
+    /// the hashed vrf output carried by the proof, see `output_to_hash`;
+    /// this does not verify the proof
+    pub fn proof_to_hash(&self) -> Result<[u8; 64]> {
+        output_to_hash(&self.v)
+    }
+}
+
+/// hash a vrf output into 64 uniformly random bytes
+/// (analogous to ECVRF_proof_to_hash in RFC 9381)
+pub fn output_to_hash(v: &VRFOutput) -> Result<[u8; 64]> {
+    let mut buf: Vec<u8> = vec![];
+    v.serialize(&mut buf)?;
+    let mut hasher = Sha512::new();
+    hasher.update("domain seperator: proof to hash");
+    hasher.update(buf);
+    let digest = hasher.finalize();
+    let mut res = [0u8; 64];
+    res.copy_from_slice(&digest);
+    Ok(res)
+}
 
-impl VRF for LBVRF {
-    type PubParam = Param;
-    type PublicKey = crate::keypair::PublicKey;
-    type SecretKey = crate::keypair::SecretKey;
-    type Proof = crate::lbvrf::Proof;
-    type VrfOutput = crate::lbvrf::VRFOutput;
+/// hash a vrf output into 32 uniformly random bytes;
+/// this is a prefix of `output_to_hash`
+pub fn output_to_hash32(v: &VRFOutput) -> Result<[u8; 32]> {
+    let digest = output_to_hash(v)?;
+    let mut res = [0u8; 32];
+    res.copy_from_slice(&digest[0..32]);
+    Ok(res)
+}
+
+/// the one time LB-VRF, instantiated with a parameter set;
+/// `LBVRF` alone uses the `Standard` set
+pub struct LBVRF<S: ParameterSet = Standard>(PhantomData<S>);
+
+impl<S: ParameterSet> VRF for LBVRF<S> {
+    type PubParam = Param<S>;
+    type PublicKey = PublicKey<S>;
+    type SecretKey = SecretKey<S>;
+    type Proof = Proof<S>;
+    type VrfOutput = VRFOutput;
 
     /// input some seed, generate public parameters
-    fn paramgen(seed: [u8; 32]) -> Result<Self::PubParam, String> {
-        let mut rng = ChaCha20Rng::from_seed(seed);
-        Ok(Param::init(&mut rng))
+    /// the matrix is expanded from the seed, see `Param::from_seed`
+    fn paramgen(seed: [u8; 32]) -> Result<Self::PubParam> {
+        Ok(Param::from_seed(seed))
     }
     /// input a seed and a parameter output a pair of keys
-    fn keygen(
-        seed: [u8; 32],
-        pp: Self::PubParam,
-    ) -> Result<(Self::PublicKey, Self::SecretKey), String> {
-        let mut rng = ChaCha20Rng::from_seed(seed);
-        let mut sk = Self::SecretKey {
-            s: [Poly256::zero(); 9],
-        };
-        for e in sk.s.iter_mut() {
-            *e = PolyArith::rand_trinary(&mut rng);
-        }
-        let mut pk = Self::PublicKey {
-            t: [Poly256::zero(); 4],
-        };
-        for i in 0..4 {
-            pk.t[i] = poly256_inner_product_trinary(&pp.matrix[i], &sk.s);
-        }
-        Ok((pk, sk))
+    fn keygen(seed: [u8; 32], pp: &Self::PubParam) -> Result<(Self::PublicKey, Self::SecretKey)> {
+        keygen(seed, pp)
     }
 
     /// input a message, a public parameter, a pair of keys
     /// generate a vrf proof
     fn prove<Blob: AsRef<[u8]>>(
         message: Blob,
-        pp: Self::PubParam,
-        pk: Self::PublicKey,
-        sk: Self::SecretKey,
+        pp: &Self::PubParam,
+        pk: &Self::PublicKey,
+        sk: &Self::SecretKey,
         seed: [u8; 32],
-    ) -> Result<Self::Proof, String> {
+    ) -> Result<Self::Proof> {
         let (proof, _rs) = prove_with_rs(message, pp, pk, sk, seed)?;
         Ok(proof)
     }
@@ -74,33 +108,108 @@ impl VRF for LBVRF {
     /// generate an output if proof is valid
     fn verify<Blob: AsRef<[u8]>>(
         message: Blob,
-        pp: Self::PubParam,
-        pk: Self::PublicKey,
-        proof: Self::Proof,
-    ) -> Result<Option<Self::VrfOutput>, String> {
+        pp: &Self::PubParam,
+        pk: &Self::PublicKey,
+        proof: &Self::Proof,
+    ) -> Result<Option<Self::VrfOutput>> {
+        rejected_as_none(verify_with_reason(message, pp, pk, proof))
+    }
+}
+
+/// a verifier for a fixed parameter and public key
+///
+/// it caches the hash state of the (param, public key) prefix and the
+/// public key in NTT domain, so that many proofs under the same key
+/// can be verified without redoing that work
+#[derive(Clone)]
+pub struct PreparedVerifier<'a, S: ParameterSet = Standard> {
+    pp: &'a Param<S>,
+    t_ntt: Vec<Poly256>,
+    prefix: Sha512,
+}
+
+impl<'a, S: ParameterSet> PreparedVerifier<'a, S> {
+    /// returns `Error::ParamMismatch` if the key belongs to another param
+    pub fn new(pp: &'a Param<S>, pk: &PublicKey<S>) -> Result<Self> {
+        check_param(pp, pk)?;
+        let mut buf: Vec<u8> = vec![];
+        pk.serialize(&mut buf)?;
+        Ok(Self::from_encoded(pp, pk, &buf))
+    }
+
+    /// as `new`, for a public key that is already serialized
+    fn from_encoded(pp: &'a Param<S>, pk: &PublicKey<S>, encoded: &[u8]) -> Self {
+        let t_ntt = pk.t.iter().map(|x| x.ntt()).collect();
+        Self {
+            pp,
+            t_ntt,
+            prefix: hash_prefix(pp, encoded),
+        }
+    }
+
+    /// input a message and a proof
+    /// generate an output if proof is valid
+    pub fn verify<Blob: AsRef<[u8]>>(
+        &self,
+        message: Blob,
+        proof: &Proof<S>,
+    ) -> Result<Option<VRFOutput>> {
+        rejected_as_none(self.verify_with_reason(message, proof))
+    }
+
+    /// input a message and a proof
+    /// generate the hashed output if proof is valid, see `output_to_hash`
+    pub fn verify_to_hash<Blob: AsRef<[u8]>>(
+        &self,
+        message: Blob,
+        proof: &Proof<S>,
+    ) -> Result<Option<[u8; 64]>> {
+        match self.verify(message, proof)? {
+            Some(v) => Ok(Some(output_to_hash(&v)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// input a message and a proof
+    /// generate an output if proof is valid; otherwise return the reason
+    /// why the proof is rejected
+    pub fn verify_with_reason<Blob: AsRef<[u8]>>(
+        &self,
+        message: Blob,
+        proof: &Proof<S>,
+    ) -> Result<VRFOutput> {
         // step 3: check the length of z
-        if !check_norm(&proof.z) {
-            return Ok(None);
+        if !check_norm(&proof.z, S::BETA_M_KAPPA) {
+            return Err(Error::NormBound);
         }
+        self.verify_challenge(message, proof, &mut vec![])
+    }
 
+    /// the remaining steps of the verification, once the norm of z is checked;
+    /// `buf` is scratch space for the hash input
+    fn verify_challenge<Blob: AsRef<[u8]>>(
+        &self,
+        message: Blob,
+        proof: &Proof<S>,
+        buf: &mut Vec<u8>,
+    ) -> Result<VRFOutput> {
         // step 0: rebuild b and z_p, c_p, v_p
-        let mut hash_input: Vec<u8> = vec![];
-        assert!(pk.serialize(&mut hash_input).is_ok());
-        hash_input = [pp.digest.as_ref(), hash_input.as_ref(), message.as_ref()].concat();
-        let mut hasher = Sha512::new();
-        hasher.update(hash_input);
+        let mut hasher = self.prefix.clone();
+        hasher.update(message.as_ref());
         let digest = hasher.finalize();
-        let b = hash_to_new_basis(digest.as_ref());
+        let b = hash_to_new_basis::<S>(digest.as_ref());
 
-        let z_p: Vec<Poly32> = proof.z.iter().map(|x| (*x).into()).collect();
+        let z_p: Vec<Poly32> = parallel::map(&proof.z, |x| (*x).into());
         let c_p: Poly32 = proof.c.into();
 
-        // step 1: compute w1_prime = A z - c t
-        let mut w1 = [Poly256::zero(); 4];
-        for (i, e) in w1.iter_mut().enumerate() {
-            *e = poly256_inner_product(&pp.matrix[i], &proof.z);
-            (*e).sub_assign(&Poly256::mul_trinary(&pk.t[i], &proof.c));
-        }
+        // step 1: compute w1_prime = A z - c t, in NTT domain
+        let z_ntt: Vec<Poly256> = parallel::map(&proof.z, |x| x.ntt());
+        let c_ntt = proof.c.ntt();
+        let w1 = parallel::zip_map(&self.pp.matrix_ntt, &self.t_ntt, |a, t| {
+            let az = poly256_inner_product_ntt_domain(a, &z_ntt);
+            let ct = pointwise_mul(t, &c_ntt);
+            Poly256::sub(&az, &ct).inverse_ntt()
+        });
 
         // step 2: compute w2_prime = <b, z> - cv
         let mut w2 = poly32_inner_product(&b, &z_p);
@@ -109,59 +218,252 @@ impl VRF for LBVRF {
         // step 3: check length of z -- done already
 
         // step 4: check c = hash(A, t, u, w1_prime, w2_prime, v)
-        let mut hash_input: Vec<u8> = vec![];
-        // todo: properly handle the errors
+        buf.clear();
         for e in w1.iter() {
-            assert!((*e).serialize(&mut hash_input).is_ok());
+            (*e).serialize(&mut *buf)?;
         }
-        assert!(w2.serialize(&mut hash_input).is_ok());
-        assert!(proof.v.serialize(&mut hash_input).is_ok());
+        w2.serialize(&mut *buf)?;
+        proof.v.serialize(&mut *buf)?;
         let mut hasher = Sha512::new();
-        hasher.update([digest.as_ref(), hash_input.as_ref()].concat());
+        hasher.update(digest);
+        hasher.update(&buf[..]);
         let digest = hasher.finalize();
-        let c = hash_to_challenge(digest.as_ref());
+        let c = hash_to_challenge::<S>(digest.as_ref())?;
         if c == proof.c {
-            Ok(Some(proof.v))
+            Ok(proof.v)
         } else {
-            #[cfg(debug_assertions)]
-            println!("verification failed");
+            Err(Error::ChallengeMismatch)
+        }
+    }
+}
+
+/// a ChaCha20 rng whose state is wiped from memory when it is dropped;
+/// used wherever the randomness is secret
+pub(crate) struct ZeroizingRng(ChaCha20Rng);
+
+impl ZeroizingRng {
+    pub(crate) fn from_seed(seed: [u8; 32]) -> Self {
+        ZeroizingRng(ChaCha20Rng::from_seed(seed))
+    }
+}
+
+impl RngCore for ZeroizingRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for ZeroizingRng {}
+
+impl Drop for ZeroizingRng {
+    fn drop(&mut self) {
+        // ChaCha20Rng does not implement Zeroize; overwrite the whole state
+        // (key, counter and buffered output) with a volatile write so that
+        // the compiler cannot elide it
+        unsafe {
+            std::ptr::write_volatile(&mut self.0, ChaCha20Rng::from_seed([0u8; 32]));
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+/// input a seed and a parameter output a pair of keys
+pub(crate) fn keygen<S: ParameterSet>(
+    seed: [u8; 32],
+    pp: &Param<S>,
+) -> Result<(PublicKey<S>, SecretKey<S>)> {
+    let mut rng = ZeroizingRng::from_seed(seed);
+    let mut sk = SecretKey {
+        s: vec![Poly256::zero(); S::L],
+        set: PhantomData,
+    };
+    for e in sk.s.iter_mut() {
+        *e = Poly256::rand_trinary(&mut rng);
+    }
+    let t = parallel::map(&pp.matrix, |e| Poly256::inner_product_trinary(e, &sk.s));
+    Ok((
+        PublicKey {
+            t,
+            digest: pp.digest,
+            set: PhantomData,
+        },
+        sk,
+    ))
+}
+
+/// input a message, a public parameter, the public key, and a proof
+/// generate an output if proof is valid; otherwise return the reason
+/// why the proof is rejected
+pub fn verify_with_reason<S: ParameterSet, Blob: AsRef<[u8]>>(
+    message: Blob,
+    pp: &Param<S>,
+    pk: &PublicKey<S>,
+    proof: &Proof<S>,
+) -> Result<VRFOutput> {
+    PreparedVerifier::new(pp, pk)?.verify_with_reason(message, proof)
+}
+
+/// input a message, a public parameter, the public key, and a proof
+/// generate the hashed output if proof is valid, see `output_to_hash`
+pub fn verify_to_hash<S: ParameterSet, Blob: AsRef<[u8]>>(
+    message: Blob,
+    pp: &Param<S>,
+    pk: &PublicKey<S>,
+    proof: &Proof<S>,
+) -> Result<Option<[u8; 64]>> {
+    PreparedVerifier::new(pp, pk)?.verify_to_hash(message, proof)
+}
+
+/// verify many proofs under the same public parameter; each entry of the
+/// batch holds a message, the public key and the proof
+///
+/// returns the output or the reason of the rejection of each proof, in the
+/// order of the batch, i.e., the results of `verify_with_reason`.
+/// the work is amortised over the batch: the norm of all proofs is checked
+/// before any hashing or NTT, the hash prefix and the NTT of a public key
+/// are computed once for all the entries that refer to it, and the NTT of the
+/// matrix is shared through the `Param`; with the `parallel` feature, the
+/// keys and the proofs are processed on all cores
+pub fn verify_batch<S: ParameterSet, Blob: AsRef<[u8]> + Sync>(
+    pp: &Param<S>,
+    batch: &[(Blob, &PublicKey<S>, &Proof<S>)],
+) -> Vec<Result<VRFOutput>> {
+    // the encoded public keys of the proofs that pass the norm check
+    let encoded = parallel::map(batch, |(_, pk, proof)| {
+        if !check_norm(&proof.z, S::BETA_M_KAPPA) {
+            return Err(Error::NormBound);
+        }
+        check_param(pp, pk)?;
+        let mut buf: Vec<u8> = vec![];
+        pk.serialize(&mut buf)?;
+        Ok(buf)
+    });
+
+    // the distinct public keys, by encoding, so that the entries that share
+    // a key only prepare it once
+    let mut index: HashMap<&[u8], usize> = HashMap::new();
+    let mut keys: Vec<(&PublicKey<S>, &[u8])> = vec![];
+    let key_of: Vec<Option<usize>> = batch
+        .iter()
+        .zip(encoded.iter())
+        .map(|((_, pk, _), buf)| {
+            let buf = buf.as_deref().ok()?;
+            Some(*index.entry(buf).or_insert_with(|| {
+                keys.push((*pk, buf));
+                keys.len() - 1
+            }))
+        })
+        .collect();
+    let verifiers = parallel::map(&keys, |(pk, buf)| {
+        PreparedVerifier::from_encoded(pp, pk, buf)
+    });
+
+    let mut res = parallel::zip_map(batch, &key_of, |(message, _, proof), i| match i {
+        Some(i) => verifiers[*i].verify_challenge(message, proof, &mut vec![]),
+        // replaced by the error of the entry below
+        None => Err(Error::NormBound),
+    });
+    for (r, buf) in res.iter_mut().zip(encoded) {
+        if let Err(e) = buf {
+            *r = Err(e);
+        }
+    }
+    res
+}
+
+/// a public key can only be used with the param it is generated under
+pub(crate) fn check_param<S: ParameterSet>(pp: &Param<S>, pk: &PublicKey<S>) -> Result<()> {
+    if pk.digest == pp.digest {
+        Ok(())
+    } else {
+        Err(Error::ParamMismatch)
+    }
+}
+
+/// hash state after absorbing the parameter digest and the encoded public key;
+/// the message is absorbed on top of it to derive the new basis
+fn hash_prefix<S: ParameterSet>(pp: &Param<S>, pk: &[u8]) -> Sha512 {
+    let mut hasher = Sha512::new();
+    hasher.update(pp.digest);
+    hasher.update(pk);
+    hasher
+}
+
+/// map the errors that indicate an invalid proof to `Ok(None)`,
+/// as required by `VRF::verify`
+pub(crate) fn rejected_as_none(res: Result<VRFOutput>) -> Result<Option<VRFOutput>> {
+    match res {
+        Ok(v) => Ok(Some(v)),
+        Err(Error::NormBound) | Err(Error::ChallengeMismatch) | Err(Error::EntropyExhausted) => {
             Ok(None)
         }
+        Err(e) => Err(e),
     }
 }
 
-pub(crate) fn hash_to_new_basis(input: &[u8]) -> [Poly32; 9] {
+pub(crate) fn hash_to_new_basis<S: ParameterSet>(input: &[u8]) -> Vec<Poly32> {
     let mut hasher = Sha512::new();
     hasher.update([input, "domain seperator: hash to basis".as_ref()].concat());
     let digest = hasher.finalize();
-    let seed: [u8; 32] = digest.as_slice()[0..32].try_into().expect("Wrong length");
+    let seed: [u8; 32] = digest[0..32].try_into().expect("Wrong length");
     let mut rng = ChaCha20Rng::from_seed(seed);
-    let mut res = [Poly32::zero(); 9];
-    for e in res.iter_mut() {
-        *e = Poly32::uniform_random(&mut rng);
-    }
-    res
+    (0..S::L)
+        .map(|_| Poly32::uniform_random(&mut rng))
+        .collect()
 }
 
-pub(crate) fn hash_to_challenge(input: &[u8]) -> Poly256 {
-    let mut hasher = Sha512::new();
-    hasher.update([input, "domain seperator: hash to challenge".as_ref()].concat());
-    let digest = hasher.finalize();
-    let mut res = [0i64; 256];
+// the positions are read from the front of the hash output, and the signs
+// from the back; the output is extended with more blocks for larger KAPPA
+pub(crate) fn hash_to_challenge<S: ParameterSet>(input: &[u8]) -> Result<Poly256> {
+    let mut digest: Vec<u8> = vec![];
+    for i in 0..challenge_blocks(S::KAPPA) {
+        let mut hasher = Sha512::new();
+        hasher.update([input, "domain seperator: hash to challenge".as_ref()].concat());
+        if i > 0 {
+            hasher.update([i as u8]);
+        }
+        digest.extend_from_slice(&hasher.finalize());
+    }
+    let len = digest.len();
+    let mut res = [0i32; 256];
     let mut sign_pt = 0;
     let mut coeff_pt = 0;
     let mut ct = 0;
-    let mut tmp = digest[63];
-    for _i in 0..KAPPA {
+    let mut tmp = digest[len - 1];
+    for _i in 0..S::KAPPA {
         let coeff = tmp & 0b1;
         tmp >>= 1;
         ct += 1;
         if ct == 4 {
             ct = 0;
             sign_pt += 1;
-            tmp = digest[64 - sign_pt - 1];
+            if coeff_pt + sign_pt >= len {
+                return Err(Error::EntropyExhausted);
+            }
+            tmp = digest[len - sign_pt - 1];
         }
-        if res[digest[coeff_pt] as usize] != 0 {
+        // skip all positions that are already taken, so that c has
+        // exactly KAPPA non-zero coefficients; the positions must not
+        // run into the signs
+        loop {
+            if coeff_pt + sign_pt >= len {
+                return Err(Error::EntropyExhausted);
+            }
+            if res[digest[coeff_pt] as usize] == 0 {
+                break;
+            }
             coeff_pt += 1;
         }
         res[digest[coeff_pt] as usize] = {
@@ -172,101 +474,155 @@ pub(crate) fn hash_to_challenge(input: &[u8]) -> Poly256 {
             }
         };
         coeff_pt += 1;
-
-        if coeff_pt + sign_pt == 64 {
-            panic!(
-                "used all entropy\n\
-                 this should never happen in practise!!!\n\
-                 (something may be wrong with prng)"
-            )
-        }
     }
 
-    Poly256 { coeff: res }
+    Ok(Poly256 { coeff: res })
 }
 
-fn check_norm(z: &[Poly256; 9]) -> bool {
+// number of 64 bytes blocks of hash output for a challenge of weight kappa:
+// kappa bytes for the positions, kappa/4 bytes for the signs, and a margin
+// of 16 bytes for the positions that are hit twice; when the margin runs
+// out the hash fails with `Error::EntropyExhausted` and the prover resamples y
+fn challenge_blocks(kappa: usize) -> usize {
+    (kappa + kappa / 4 + 16).div_ceil(64)
+}
+
+// z is a secret until it passes the check, so we scan all coefficients
+// and only branch on the accumulated result
+pub(crate) fn check_norm(z: &[Poly256], bound: i32) -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if avx2::enabled() {
+            return unsafe { avx2::check_norm(z, bound) };
+        }
+    }
+    let mut out = 0;
     for e in z.iter() {
         for f in e.coeff.iter() {
-            if *f > BETA_M_KAPPA || *f < -BETA_M_KAPPA {
-                return false;
-            }
+            out |= ct::out_of_bound(*f, bound);
         }
     }
 
-    true
+    out == 0
 }
 
 /// input a message, a public parameter, a pair of keys
-/// generate a vrf proof
-pub(crate) fn prove_with_rs<Blob: AsRef<[u8]>>(
+/// generate a vrf proof, and the number of rejection sampling attempts
+pub(crate) fn prove_with_rs<S: ParameterSet, Blob: AsRef<[u8]>>(
     message: Blob,
-    pp: Param,
-    pk: crate::keypair::PublicKey,
-    sk: crate::keypair::SecretKey,
+    pp: &Param<S>,
+    pk: &PublicKey<S>,
+    sk: &SecretKey<S>,
     seed: [u8; 32],
-) -> Result<(Proof, usize), String> {
-    let mut rng = ChaCha20Rng::from_seed(seed);
-    let mut y = [Poly256::zero(); 9];
+) -> Result<(Proof<S>, usize)> {
+    prove_with_window(message, pp, pk, sk, seed, parallel::threads())
+}
+
+/// as `prove_with_rs`, evaluating `window` attempts of the rejection sampling
+/// at a time; the y of the attempts are sampled from the seeded rng in order
+/// and the first accepted attempt is returned, so the proof, and the number
+/// of attempts, do not depend on the window
+pub(crate) fn prove_with_window<S: ParameterSet, Blob: AsRef<[u8]>>(
+    message: Blob,
+    pp: &Param<S>,
+    pk: &PublicKey<S>,
+    sk: &SecretKey<S>,
+    seed: [u8; 32],
+    window: usize,
+) -> Result<(Proof<S>, usize)> {
+    // all secret dependent buffers are wiped when they go out of scope
+    let mut rng = ZeroizingRng::from_seed(seed);
     let mut rs = 0;
     // step 0: s_p = s mod (p, x^32+R)
-    let s_p: Vec<Poly32> = sk.s.iter().map(|x| (*x).into()).collect();
+    check_param(pp, pk)?;
+    let s_p: Zeroizing<Vec<Poly32>> = Zeroizing::new(sk.s.iter().map(|x| x.into()).collect());
 
     // step 1: b = hash_to_new_basis (pp, pk, message)
-    let mut hash_input: Vec<u8> = vec![];
-    assert!(pk.serialize(&mut hash_input).is_ok());
-    hash_input = [pp.digest.as_ref(), hash_input.as_ref(), message.as_ref()].concat();
-    let mut hasher = Sha512::new();
-    hasher.update(hash_input);
+    let mut buf: Vec<u8> = vec![];
+    pk.serialize(&mut buf)?;
+    let mut hasher = hash_prefix(pp, &buf);
+    hasher.update(message.as_ref());
     let digest = hasher.finalize();
-    let b = hash_to_new_basis(digest.as_ref());
+    let b = hash_to_new_basis::<S>(digest.as_ref());
 
     // step 2: v = <b, s>
     let v = poly32_inner_product(&b, &s_p);
 
     // we start rejection sampling here
     loop {
-        rs += 1;
-        // step 3: sample y
-        for e in y.iter_mut() {
-            *e = Poly256::rand_mod_beta(&mut rng);
+        // step 3: sample y, for the next attempts
+        let y: Vec<Zeroizing<Vec<Poly256>>> = (0..window.max(1))
+            .map(|_| {
+                Zeroizing::new(
+                    (0..S::L)
+                        .map(|_| rand_mod_bound(&mut rng, S::BETA, S::BETA_M2_P1, S::BETA_RS_RANGE))
+                        .collect(),
+                )
+            })
+            .collect();
+        let res = parallel::map(&y, |y| prove_attempt(pp, sk, &b, &v, &digest, y));
+        for e in res {
+            rs += 1;
+            if let Some(proof) = e? {
+                return Ok((proof, rs));
+            }
         }
-        let y_p: Vec<Poly32> = y.iter().map(|x| (*x).into()).collect();
+    }
+}
 
-        // step 4: w1 = Ay, w2 = by
-        let mut w1 = [Poly256::zero(); 4];
-        for (i, e) in w1.iter_mut().enumerate() {
-            *e = poly256_inner_product(&pp.matrix[i], &y);
-        }
-        let w2 = poly32_inner_product(&b, &y_p);
+/// steps 4 to 6 of the proof for a sampled y;
+/// returns None if z is rejected
+fn prove_attempt<S: ParameterSet>(
+    pp: &Param<S>,
+    sk: &SecretKey<S>,
+    b: &[Poly32],
+    v: &VRFOutput,
+    digest: &[u8],
+    y: &[Poly256],
+) -> Result<Option<Proof<S>>> {
+    let y_p: Zeroizing<Vec<Poly32>> = Zeroizing::new(parallel::map(y, |x| x.into()));
 
-        // step 5: c = hash_to_challenge(pp, pk, message, w1, w2, v)
-        let mut hash_input: Vec<u8> = vec![];
+    // step 4: w1 = Ay, w2 = by
+    let y_ntt: Zeroizing<Vec<Poly256>> = Zeroizing::new(parallel::map(y, |x| x.ntt()));
+    let w1 = Zeroizing::new(parallel::map(&pp.matrix_ntt, |e| {
+        poly256_inner_product_ntt(e, &y_ntt)
+    }));
+    let w2 = Zeroizing::new(poly32_inner_product(b, &y_p));
 
-        // todo: properly handle the errors
-        for e in w1.iter() {
-            assert!((*e).serialize(&mut hash_input).is_ok());
-        }
-        assert!(w2.serialize(&mut hash_input).is_ok());
-        assert!(v.serialize(&mut hash_input).is_ok());
-        let mut hasher = Sha512::new();
-        hasher.update([digest.as_ref(), hash_input.as_ref()].concat());
-        let digest = hasher.finalize();
-        let c = hash_to_challenge(digest.as_ref());
+    // step 5: c = hash_to_challenge(pp, pk, message, w1, w2, v)
+    let mut hash_input: Zeroizing<Vec<u8>> = Zeroizing::new(vec![]);
+    for e in w1.iter() {
+        (*e).serialize(&mut *hash_input)?;
+    }
+    w2.serialize(&mut *hash_input)?;
+    v.serialize(&mut *hash_input)?;
+    let mut hasher = Sha512::new();
+    hasher.update(digest);
+    hasher.update(hash_input.as_slice());
+    let digest = hasher.finalize();
+    // a hash that runs out of entropy rejects the attempt, as a long z does
+    let c = match hash_to_challenge::<S>(digest.as_ref()) {
+        Ok(c) => c,
+        Err(Error::EntropyExhausted) => return Ok(None),
+        Err(e) => return Err(e),
+    };
 
-        let mut z = y;
-        for (i, e) in z.iter_mut().enumerate() {
-            (*e).add_assign(&PolyArith::mul_trinary(&sk.s[i], &c));
-        }
-        for e in z.iter_mut() {
-            (*e).centered();
-        }
-        // #[cfg(debug_assertions)]
-        // println!("rejection sampling {} times", rs);
-        // #[cfg(debug_assertions)]
-        // println!("z:\n{:?}", z);
-        if check_norm(&z) {
-            return Ok((Proof { z, c, v }, rs));
-        }
+    // step 6: z = y + sc
+    // a rejected z leaks information about s
+    let sc = Zeroizing::new(parallel::map(&sk.s, |e| Poly256::mul_trinary(e, &c)));
+    let mut z = Zeroizing::new(y.to_vec());
+    for (e, f) in z.iter_mut().zip(sc.iter()) {
+        (*e).add_assign(f);
+        (*e).centered();
+    }
+    if check_norm(&z, S::BETA_M_KAPPA) {
+        Ok(Some(Proof {
+            z: z.to_vec(),
+            c,
+            v: *v,
+            set: PhantomData,
+        }))
+    } else {
+        Ok(None)
     }
 }
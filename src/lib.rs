@@ -3,17 +3,30 @@
 extern crate rand;
 extern crate rand_chacha;
 extern crate sha2;
+#[cfg(target_arch = "x86_64")]
+mod avx2;
+pub(crate) mod ct;
+pub mod ecvrf;
+pub mod envelope;
+pub mod error;
+pub mod estimate;
+pub mod hybrid;
 pub mod keypair;
 pub mod lbvrf;
+pub mod mtlbvrf;
 pub mod ntt;
+pub(crate) mod parallel;
 pub mod param;
 pub mod poly;
 pub mod poly256;
 pub mod poly32;
+pub mod reduce;
 pub mod serde;
 #[cfg(test)]
 mod test;
 
+pub use error::Error;
+
 pub trait VRF {
     type PubParam;
     type PublicKey;
@@ -22,29 +35,30 @@ pub trait VRF {
     type VrfOutput;
 
     /// input some seed, generate public parameters
-    fn paramgen(seed: [u8; 32]) -> Result<Self::PubParam, String>;
+    fn paramgen(seed: [u8; 32]) -> Result<Self::PubParam, Error>;
     /// input a seed and a parameter output a pair of keys
     fn keygen(
         seed: [u8; 32],
-        pp: Self::PubParam,
-    ) -> Result<(Self::PublicKey, Self::SecretKey), String>;
+        pp: &Self::PubParam,
+    ) -> Result<(Self::PublicKey, Self::SecretKey), Error>;
 
     /// input a message, a public parameter, a pair of keys
     /// generate a vrf proof
     fn prove<Blob: AsRef<[u8]>>(
         message: Blob,
-        pp: Self::PubParam,
-        pk: Self::PublicKey,
-        sk: Self::SecretKey,
+        pp: &Self::PubParam,
+        pk: &Self::PublicKey,
+        sk: &Self::SecretKey,
         seed: [u8; 32],
-    ) -> Result<Self::Proof, String>;
+    ) -> Result<Self::Proof, Error>;
 
     /// input a message, a public parameter, the public key, and a proof
-    /// generate an output if proof is valid
+    /// generate an output if proof is valid, or None if the proof is rejected;
+    /// errors are returned for inputs that do not belong together
     fn verify<Blob: AsRef<[u8]>>(
         message: Blob,
-        pp: Self::PubParam,
-        pk: Self::PublicKey,
-        proof: Self::Proof,
-    ) -> Result<Option<Self::VrfOutput>, String>;
+        pp: &Self::PubParam,
+        pk: &Self::PublicKey,
+        proof: &Self::Proof,
+    ) -> Result<Option<Self::VrfOutput>, Error>;
 }
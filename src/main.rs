@@ -1,3 +1,547 @@
+// command line tool for the one time LB-VRF
+//
+// all objects are read and written with their `Serdes` encodings; the
+// `--format` option selects whether the files hold the raw bytes, the bytes
+// in hex or base64, or an armored envelope (see `lb_vrf::envelope`) that also
+// binds keys and proofs to the param. a path of `-` stands for stdin/stdout.
+//
+// a one time secret key leaks when it proves two messages: `prove` creates
+// a `<sk>.used` marker next to the key and refuses keys that have one,
+// unless `--allow-reuse` is given.
+//
+// exit codes: 0 on success, 1 if a proof is rejected, 2 on any other error
+
+use lb_vrf::envelope::{self, Envelope, Header, ObjectType};
+use lb_vrf::estimate::Setting;
+use lb_vrf::keypair::{PublicKey, SecretKey};
+use lb_vrf::lbvrf::{Proof, LBVRF};
+use lb_vrf::param::{HighSecurity, Param, ParameterSet, Standard};
+use lb_vrf::serde::Serdes;
+use lb_vrf::VRF;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+use zeroize::Zeroizing;
+
+const USAGE: &str = "usage: lb-vrf <command> [options]
+
+commands:
+    paramgen  --out <file> [--seed <hex>]
+    keygen    --param <file> --pk <file> --sk <file> [--seed <hex>]
+    prove     --param <file> --pk <file> --sk <file> --out <file>
+              [--message <file>] [--seed <hex>] [--allow-reuse]
+    verify    --param <file> --pk <file> --proof <file> [--message <file>]
+    inspect   [--param <file>] [--pk <file>] [--sk <file>] [--proof <file>]
+    estimate  [--set <standard|high>]
+
+options:
+    --format <raw|hex|base64|pem>  encoding of all files, defaults to raw
+    --seed <hex>                   32 bytes seed, sampled from the OS if omitted
+    --message <file>               message to prove/verify, defaults to stdin
+    --allow-reuse                  prove with a secret key that was already used
+
+a file of `-` stands for stdin/stdout.
+the secret key of the one time scheme must prove a single message; prove
+marks it as used with a `<sk>.used` file. a key read from stdin cannot be
+marked and requires `--allow-reuse`.
+verify prints the hashed vrf output in hex and exits with 1
+if the proof is rejected.";
+
+// options without a value
+const FLAGS: [&str; 1] = ["allow-reuse"];
+
+const EXIT_REJECTED: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        match &e {
+            CliError::Rejected => eprintln!("proof rejected"),
+            CliError::Usage(msg) => eprintln!("{}\n\n{}", msg, USAGE),
+            _ => eprintln!("error: {}", e),
+        }
+        process::exit(e.exit_code());
+    }
+}
+
+#[derive(Debug)]
+enum CliError {
+    /// the command line is invalid
+    Usage(String),
+    /// a file could not be read or written
+    Io(String, io::Error),
+    /// a file holds an invalid encoding
+    Decode(String, String),
+    /// an error from the vrf
+    Vrf(lb_vrf::Error),
+    /// a one time secret key would be used twice
+    KeyReuse(String),
+    /// the proof is rejected
+    Rejected,
+}
+
+impl CliError {
+    fn exit_code(&self) -> i32 {
+        match self {
+            CliError::Rejected => EXIT_REJECTED,
+            _ => EXIT_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(e) => write!(f, "{}", e),
+            CliError::Io(path, e) => write!(f, "{}: {}", path, e),
+            CliError::Decode(path, e) => write!(f, "{}: {}", path, e),
+            CliError::Vrf(e) => write!(f, "{}", e),
+            CliError::KeyReuse(e) => write!(f, "{}", e),
+            CliError::Rejected => write!(f, "proof rejected"),
+        }
+    }
+}
+
+impl From<lb_vrf::Error> for CliError {
+    fn from(e: lb_vrf::Error) -> Self {
+        CliError::Vrf(e)
+    }
+}
+
+type CliResult<T> = std::result::Result<T, CliError>;
+
+/// encoding of the files
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Raw,
+    Hex,
+    Base64,
+    /// an armored envelope
+    Pem,
+}
+
+impl Format {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "raw" => Some(Format::Raw),
+            "hex" => Some(Format::Hex),
+            "base64" => Some(Format::Base64),
+            "pem" => Some(Format::Pem),
+            _ => None,
+        }
+    }
+
+    // all buffers are wiped when dropped, as they may hold a secret key
+    fn encode(self, bytes: &[u8]) -> Zeroizing<Vec<u8>> {
+        let text = match self {
+            Format::Raw => return Zeroizing::new(bytes.to_vec()),
+            Format::Hex => Zeroizing::new(hex::encode(bytes) + "\n"),
+            Format::Base64 => Zeroizing::new(base64::encode(bytes) + "\n"),
+            // the bytes are sealed by `Options::write`
+            Format::Pem => envelope::armor(bytes).expect("an envelope"),
+        };
+        Zeroizing::new(text.as_bytes().to_vec())
+    }
+
+    // surrounding whitespaces are ignored for the text formats
+    fn decode(self, bytes: &[u8]) -> std::result::Result<Zeroizing<Vec<u8>>, String> {
+        let res = match self {
+            Format::Raw => Ok(bytes.to_vec()),
+            Format::Hex => hex::decode(trim_ascii(bytes)).map_err(|e| e.to_string()),
+            Format::Base64 => base64::decode(trim_ascii(bytes)).map_err(|e| e.to_string()),
+            Format::Pem => {
+                let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+                return envelope::dearmor(text).map_err(|e| e.to_string());
+            }
+        };
+        res.map(Zeroizing::new)
+    }
+}
+
+fn trim_ascii(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|e| !e.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|e| !e.is_ascii_whitespace())
+        .map_or(start, |e| e + 1);
+    &bytes[start..end]
+}
+
+/// parsed `--key value` options
+struct Options {
+    opts: HashMap<String, String>,
+    format: Format,
+}
+
+impl Options {
+    fn parse(args: &[String], allowed: &[&str]) -> CliResult<Self> {
+        let mut opts = HashMap::new();
+        let mut iter = args.iter();
+        while let Some(key) = iter.next() {
+            let name = match key.strip_prefix("--") {
+                Some(p) if p == "format" || allowed.contains(&p) => p,
+                _ => return Err(CliError::Usage(format!("unexpected argument `{}`", key))),
+            };
+            let value = if FLAGS.contains(&name) {
+                String::new()
+            } else {
+                match iter.next() {
+                    Some(p) => p.clone(),
+                    None => return Err(CliError::Usage(format!("missing value for `{}`", key))),
+                }
+            };
+            if opts.insert(name.to_string(), value).is_some() {
+                return Err(CliError::Usage(format!("duplicated option `{}`", key)));
+            }
+        }
+        let format = match opts.get("format") {
+            Some(p) => match Format::parse(p) {
+                Some(f) => f,
+                None => return Err(CliError::Usage(format!("unknown format `{}`", p))),
+            },
+            None => Format::Raw,
+        };
+        Ok(Self { opts, format })
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.opts.get(key).map(|e| e.as_str())
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.opts.contains_key(key)
+    }
+
+    fn required(&self, key: &str) -> CliResult<&str> {
+        match self.get(key) {
+            Some(p) => Ok(p),
+            None => Err(CliError::Usage(format!("missing option `--{}`", key))),
+        }
+    }
+
+    /// the seed given with `--seed`, or a fresh one from the OS
+    fn seed(&self) -> CliResult<[u8; 32]> {
+        let mut seed = [0u8; 32];
+        match self.get("seed") {
+            Some(p) => {
+                let bytes =
+                    hex::decode(p).map_err(|e| CliError::Usage(format!("invalid seed: {}", e)))?;
+                if bytes.len() != 32 {
+                    return Err(CliError::Usage("seed must be 32 bytes".to_string()));
+                }
+                seed.copy_from_slice(&bytes);
+            }
+            None => OsRng.fill_bytes(&mut seed),
+        }
+        Ok(seed)
+    }
+
+    /// read and decode an object from the file given with `--key`; an
+    /// envelope must be bound to the param of the given digest, if any
+    fn read<T: Envelope>(&self, key: &str, digest: Option<&[u8; 32]>) -> CliResult<T> {
+        let path = self.required(key)?;
+        let file = Zeroizing::new(read_file(path)?);
+        let bytes = self
+            .format
+            .decode(&file)
+            .map_err(|e| CliError::Decode(path.to_string(), e))?;
+        if self.format == Format::Pem {
+            let decode_error = |e: lb_vrf::Error| CliError::Decode(path.to_string(), e.to_string());
+            let header = Header::parse(&bytes).map_err(decode_error)?;
+            return envelope::open(&bytes, digest.unwrap_or(&header.digest)).map_err(decode_error);
+        }
+        let mut reader = bytes.as_slice();
+        let res = T::deserialize(&mut reader).map_err(|e| match e {
+            lb_vrf::Error::Io(_) => CliError::Decode(path.to_string(), "truncated".to_string()),
+            _ => CliError::Decode(path.to_string(), e.to_string()),
+        })?;
+        if !reader.is_empty() {
+            return Err(CliError::Decode(
+                path.to_string(),
+                "trailing bytes".to_string(),
+            ));
+        }
+        Ok(res)
+    }
+
+    /// encode and write an object to the file given with `--key`; an
+    /// envelope is bound to the param of the given digest
+    fn write<T: Envelope>(&self, key: &str, obj: &T, digest: &[u8; 32]) -> CliResult<()> {
+        let path = self.required(key)?;
+        let bytes = if self.format == Format::Pem {
+            envelope::seal(obj, digest)?
+        } else {
+            let mut bytes = Zeroizing::new(vec![]);
+            obj.serialize(&mut *bytes)?;
+            bytes
+        };
+        let secret = T::OBJECT_TYPE == ObjectType::SecretKey;
+        write_file(path, &self.format.encode(&bytes), secret)
+    }
+
+    /// the message from the file given with `--message`, or stdin
+    fn message(&self) -> CliResult<Vec<u8>> {
+        read_file(self.get("message").unwrap_or("-"))
+    }
+}
+
+fn read_file(path: &str) -> CliResult<Vec<u8>> {
+    let res = if path == "-" {
+        let mut buf = vec![];
+        io::stdin().read_to_end(&mut buf).map(|_| buf)
+    } else {
+        fs::read(path)
+    };
+    res.map_err(|e| CliError::Io(path.to_string(), e))
+}
+
+// a secret file is only readable by its owner
+fn write_file(path: &str, bytes: &[u8], secret: bool) -> CliResult<()> {
+    let res = if path == "-" {
+        io::stdout().write_all(bytes)
+    } else if secret {
+        write_secret_file(path, bytes)
+    } else {
+        fs::write(path, bytes)
+    };
+    res.map_err(|e| CliError::Io(path.to_string(), e))
+}
+
+#[cfg(unix)]
+fn write_secret_file(path: &str, bytes: &[u8]) -> io::Result<()> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // the mode only applies to a new file
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(bytes)
+}
+
+#[cfg(not(unix))]
+fn write_secret_file(path: &str, bytes: &[u8]) -> io::Result<()> {
+    fs::write(path, bytes)
+}
+
+fn run(args: &[String]) -> CliResult<()> {
+    let (command, rest) = match args.split_first() {
+        Some(p) => p,
+        None => return Err(CliError::Usage("missing command".to_string())),
+    };
+    match command.as_str() {
+        "paramgen" => paramgen(&Options::parse(rest, &["out", "seed"])?),
+        "keygen" => keygen(&Options::parse(rest, &["param", "pk", "sk", "seed"])?),
+        "prove" => prove(&Options::parse(
+            rest,
+            &["param", "pk", "sk", "out", "message", "seed", "allow-reuse"],
+        )?),
+        "verify" => verify(&Options::parse(rest, &["param", "pk", "proof", "message"])?),
+        "inspect" => inspect(&Options::parse(rest, &["param", "pk", "sk", "proof"])?),
+        "estimate" => estimate(&Options::parse(rest, &["set"])?),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(CliError::Usage(format!("unknown command `{}`", command))),
+    }
+}
+
+fn paramgen(opts: &Options) -> CliResult<()> {
+    let param: Param = LBVRF::paramgen(opts.seed()?)?;
+    opts.write("out", &param, &param.digest)
+}
+
+fn keygen(opts: &Options) -> CliResult<()> {
+    let param: Param = opts.read("param", None)?;
+    let (pk, sk) = LBVRF::keygen(opts.seed()?, &param)?;
+    opts.write("pk", &pk, &param.digest)?;
+    opts.write("sk", &sk, &param.digest)
+}
+
+fn prove(opts: &Options) -> CliResult<()> {
+    let param: Param = opts.read("param", None)?;
+    let pk: PublicKey = opts.read("pk", Some(&param.digest))?;
+    let sk: SecretKey = opts.read("sk", Some(&param.digest))?;
+    let message = opts.message()?;
+    // the key is not burnt by inputs that do not belong together
+    if pk.param_digest() != param.digest {
+        return Err(lb_vrf::Error::ParamMismatch.into());
+    }
+    if !opts.flag("allow-reuse") {
+        mark_used(opts.required("sk")?)?;
+    }
+    let proof = LBVRF::prove(message, &param, &pk, &sk, opts.seed()?)?;
+    opts.write("out", &proof, &param.digest)
+}
+
+// create the `<sk>.used` marker of a one time secret key, or fail if the key
+// was already used; the marker is created before the proof, so that a key is
+// never used twice even if proving fails half way
+fn mark_used(sk: &str) -> CliResult<()> {
+    if sk == "-" {
+        return Err(CliError::KeyReuse(
+            "a secret key from stdin cannot be marked as used, pass `--allow-reuse`".to_string(),
+        ));
+    }
+    let marker = format!("{}.used", sk);
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&marker)
+    {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(CliError::KeyReuse(format!(
+            "{}: the one time secret key was already used (see {}), \
+             proving again leaks it; pass `--allow-reuse` to proceed anyway",
+            sk, marker
+        ))),
+        Err(e) => Err(CliError::Io(marker, e)),
+    }
+}
+
+fn verify(opts: &Options) -> CliResult<()> {
+    let param: Param = opts.read("param", None)?;
+    let pk: PublicKey = opts.read("pk", Some(&param.digest))?;
+    let proof: Proof = opts.read("proof", Some(&param.digest))?;
+    let message = opts.message()?;
+    match lb_vrf::lbvrf::verify_to_hash(message, &param, &pk, &proof)? {
+        Some(p) => {
+            println!("{}", hex::encode(&p[..]));
+            Ok(())
+        }
+        None => Err(CliError::Rejected),
+    }
+}
+
+// print a human readable summary of the given objects;
+// secret keys are checked for well-formedness but never printed
+fn inspect(opts: &Options) -> CliResult<()> {
+    let mut found = false;
+    // envelopes are checked against the param, if given
+    let mut digest = None;
+    if opts.get("param").is_some() {
+        let param: Param = opts.read("param", None)?;
+        println!("param:");
+        match param.seed() {
+            Some(p) => println!("  seed:   {}", hex::encode(p)),
+            None => println!("  seed:   none (full matrix)"),
+        }
+        println!("  digest: {}", hex::encode(param.digest));
+        digest = Some(param.digest);
+        found = true;
+    }
+    if opts.get("pk").is_some() {
+        let pk: PublicKey = opts.read("pk", digest.as_ref())?;
+        println!("public key:");
+        println!("  size:   {} bytes", encoded_len(&pk)?);
+        println!("  param:  {}", hex::encode(pk.param_digest()));
+        println!("  fingerprint: {}", hex::encode(pk.fingerprint()?));
+        found = true;
+    }
+    if opts.get("sk").is_some() {
+        let sk: SecretKey = opts.read("sk", digest.as_ref())?;
+        println!("secret key:");
+        println!("  size:   {} bytes", encoded_len(&sk)?);
+        found = true;
+    }
+    if opts.get("proof").is_some() {
+        let proof: Proof = opts.read("proof", digest.as_ref())?;
+        println!("proof:");
+        println!("  size:   {} bytes", encoded_len(&proof)?);
+        println!(
+            "  output: {} (unverified)",
+            hex::encode(&proof.proof_to_hash()?[..])
+        );
+        found = true;
+    }
+    if !found {
+        return Err(CliError::Usage("nothing to inspect".to_string()));
+    }
+    Ok(())
+}
+
+// print the derived constants and the security estimate of the parameter sets
+fn estimate(opts: &Options) -> CliResult<()> {
+    fn print<S: ParameterSet>() {
+        println!("{}:", S::NAME);
+        println!("{}", Setting::from_set::<S>().estimate());
+    }
+    match opts.get("set") {
+        Some("standard") => print::<Standard>(),
+        Some("high") => print::<HighSecurity>(),
+        Some(p) => return Err(CliError::Usage(format!("unknown parameter set `{}`", p))),
+        None => {
+            print::<Standard>();
+            print::<HighSecurity>();
+        }
+    }
+    Ok(())
+}
+
+fn encoded_len<T: Serdes>(obj: &T) -> CliResult<usize> {
+    let mut buf = vec![];
+    obj.serialize(&mut buf)?;
+    Ok(buf.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for format in [Format::Raw, Format::Hex, Format::Base64].iter() {
+            let encoded = format.encode(&bytes);
+            assert_eq!(*format.decode(&encoded).unwrap(), bytes);
+        }
+        assert_eq!(*Format::Hex.decode(b"  0a0b\r\n").unwrap(), vec![10, 11]);
+        assert!(Format::Hex.decode(b"0g").is_err());
+        assert!(Format::Base64.decode(b"!!!!").is_err());
+
+        let param: Param = LBVRF::paramgen([0u8; 32]).unwrap();
+        let sealed = envelope::seal(&param, &param.digest).unwrap();
+        let encoded = Format::Pem.encode(&sealed);
+        assert_eq!(Format::Pem.decode(&encoded).unwrap(), sealed);
+        assert!(Format::Pem.decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_options() {
+        let args: Vec<String> = ["--param", "a", "--format", "hex"]
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        let opts = Options::parse(&args, &["param"]).unwrap();
+        assert_eq!(opts.get("param"), Some("a"));
+        assert_eq!(opts.format, Format::Hex);
+        assert!(opts.required("pk").is_err());
+
+        assert!(Options::parse(&args, &["pk"]).is_err());
+        assert!(Options::parse(&args[0..1], &["param"]).is_err());
+        let args: Vec<String> = ["--seed", "00"].iter().map(|e| e.to_string()).collect();
+        let opts = Options::parse(&args, &["seed"]).unwrap();
+        assert!(opts.seed().is_err());
+
+        // flags take no value
+        let args: Vec<String> = ["--allow-reuse", "--sk", "a"]
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        let opts = Options::parse(&args, &["sk", "allow-reuse"]).unwrap();
+        assert!(opts.flag("allow-reuse"));
+        assert_eq!(opts.get("sk"), Some("a"));
+        assert!(Options::parse(&args, &["sk"]).is_err());
+    }
 }
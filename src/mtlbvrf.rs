@@ -0,0 +1,259 @@
+// this file implements a many time VRF scheme on top of the one time LB-VRF
+//
+// the signer generates 2^height one time key pairs from a single seed, and
+// commits to them with a Merkle tree; the public key is the root of the tree.
+// each proof uses a fresh leaf and carries the one time public key together
+// with its authentication path.
+//
+// the input of the scheme is an epoch followed by a message, see
+// `epoch_input`; the epoch is the index of the leaf. the verifier rejects a
+// proof under any other leaf, and the one time proof is over the whole input,
+// so that an input has a unique output, as in the one time scheme. the
+// epochs of a key must be used in increasing order.
+
+use crate::error::{Error, Result};
+use crate::keypair::{MTPublicKey, MTSecretKey, PublicKey, SecretKey};
+use crate::lbvrf::{keygen, prove_with_rs, rejected_as_none, verify_with_reason, Proof, VRFOutput};
+use crate::param::Param;
+use crate::serde::Serdes;
+use crate::VRF;
+use sha2::{Digest, Sha512};
+use std::convert::TryInto;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, OnceLock};
+use zeroize::{Zeroize, Zeroizing};
+
+/// default height of the Merkle tree, i.e., 1024 proofs per key
+pub const MT_HEIGHT: usize = 10;
+
+/// largest supported height of the Merkle tree
+pub const MT_MAX_HEIGHT: usize = 20;
+
+/// public parameters of the many time scheme
+#[derive(Clone, Debug, PartialEq)]
+pub struct MTParam {
+    pub param: Param,
+    pub height: usize,
+}
+
+impl MTParam {
+    pub fn new(param: Param, height: usize) -> Result<Self> {
+        if height == 0 || height > MT_MAX_HEIGHT {
+            return Err(Error::InvalidParam("invalid Merkle tree height"));
+        }
+        Ok(Self { param, height })
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct MTProof {
+    pub(crate) index: usize,
+    pub(crate) pk: PublicKey,
+    pub(crate) proof: Proof,
+    pub(crate) path: Vec<[u8; 32]>,
+}
+
+impl MTProof {
+    /// the leaf that was used to generate this proof
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// the vrf output carried by the proof;
+    /// this does not verify the proof
+    pub fn output(&self) -> VRFOutput {
+        self.proof.output()
+    }
+
+    /// the hashed vrf output carried by the proof;
+    /// this does not verify the proof
+    pub fn proof_to_hash(&self) -> Result<[u8; 64]> {
+        self.proof.proof_to_hash()
+    }
+}
+
+pub struct MTLBVRF;
+
+impl VRF for MTLBVRF {
+    type PubParam = MTParam;
+    type PublicKey = MTPublicKey;
+    type SecretKey = MTSecretKey;
+    type Proof = MTProof;
+    type VrfOutput = VRFOutput;
+
+    /// input some seed, generate public parameters
+    /// the matrix is expanded from the seed, see `Param::from_seed`
+    fn paramgen(seed: [u8; 32]) -> Result<Self::PubParam> {
+        MTParam::new(Param::from_seed(seed), MT_HEIGHT)
+    }
+
+    /// input a seed and a parameter output a pair of keys
+    /// this generates all 2^height one time keys
+    fn keygen(seed: [u8; 32], pp: &Self::PubParam) -> Result<(Self::PublicKey, Self::SecretKey)> {
+        let nodes = build_tree(&seed, pp.height, &pp.param)?;
+
+        let pk = MTPublicKey { root: nodes[1] };
+        let sk = MTSecretKey {
+            seed,
+            height: pp.height,
+            next_leaf: Arc::new(AtomicUsize::new(0)),
+            nodes: Arc::new(OnceLock::from(nodes)),
+        };
+        Ok((pk, sk))
+    }
+
+    /// input an epoch and a message (see `epoch_input`), a public parameter,
+    /// a pair of keys; generate a vrf proof using the leaf of the epoch
+    fn prove<Blob: AsRef<[u8]>>(
+        message: Blob,
+        pp: &Self::PubParam,
+        pk: &Self::PublicKey,
+        sk: &Self::SecretKey,
+        seed: [u8; 32],
+    ) -> Result<Self::Proof> {
+        if sk.height != pp.height {
+            return Err(Error::ParamMismatch);
+        }
+        // the tree is not part of the serialized secret key;
+        // rebuild it on first use
+        let nodes = match sk.nodes.get() {
+            Some(p) => p,
+            None => {
+                let nodes = build_tree(&sk.seed, sk.height, &pp.param)?;
+                sk.nodes.get_or_init(|| nodes)
+            }
+        };
+        if nodes[1] != pk.root {
+            return Err(Error::ParamMismatch);
+        }
+        let epoch = split_epoch(message.as_ref())?;
+        let index = sk.reserve_leaf(epoch)?;
+
+        let (ot_pk, ot_sk) = leaf_keygen(&sk.seed, index, &pp.param)?;
+        let (proof, _rs) = prove_with_rs(message, &pp.param, &ot_pk, &ot_sk, seed)?;
+
+        let leaves = 1 << sk.height;
+        let mut path = Vec::with_capacity(sk.height);
+        let mut pos = leaves + index;
+        while pos > 1 {
+            path.push(nodes[pos ^ 1]);
+            pos >>= 1;
+        }
+
+        Ok(MTProof {
+            index,
+            pk: ot_pk,
+            proof,
+            path,
+        })
+    }
+
+    /// input an epoch and a message (see `epoch_input`), a public parameter,
+    /// the public key, and a proof; generate an output if proof is valid
+    fn verify<Blob: AsRef<[u8]>>(
+        message: Blob,
+        pp: &Self::PubParam,
+        pk: &Self::PublicKey,
+        proof: &Self::Proof,
+    ) -> Result<Option<Self::VrfOutput>> {
+        // step 0: the proof must use the leaf of the epoch
+        let epoch = split_epoch(message.as_ref())?;
+        if proof.index as u64 != epoch {
+            return Ok(None);
+        }
+
+        // step 1: check the one time public key is a leaf of the tree
+        if proof.path.len() != pp.height || proof.index >= 1 << pp.height {
+            return Ok(None);
+        }
+        let mut node = hash_leaf(&proof.pk)?;
+        let mut pos = proof.index;
+        for e in proof.path.iter() {
+            node = if pos & 1 == 0 {
+                hash_node(&node, e)
+            } else {
+                hash_node(e, &node)
+            };
+            pos >>= 1;
+        }
+        if node != pk.root {
+            return Ok(None);
+        }
+
+        // step 2: check the one time proof
+        rejected_as_none(verify_with_reason(
+            message,
+            &pp.param,
+            &proof.pk,
+            &proof.proof,
+        ))
+    }
+}
+
+/// the input of the many time scheme: the epoch, as 8 bytes big endian,
+/// followed by the message
+pub fn epoch_input<Blob: AsRef<[u8]>>(epoch: u64, message: Blob) -> Vec<u8> {
+    [epoch.to_be_bytes().as_ref(), message.as_ref()].concat()
+}
+
+/// the epoch of an input built with `epoch_input`
+fn split_epoch(input: &[u8]) -> Result<u64> {
+    match input.get(0..8) {
+        Some(p) => Ok(u64::from_be_bytes(p.try_into().expect("8 bytes"))),
+        None => Err(Error::MalformedEncoding("missing epoch")),
+    }
+}
+
+/// derive the one time key pair for a given leaf
+fn leaf_keygen(seed: &[u8; 32], index: usize, pp: &Param) -> Result<(PublicKey, SecretKey)> {
+    let mut hasher = Sha512::new();
+    hasher.update(seed);
+    hasher.update((index as u64).to_be_bytes());
+    hasher.update("domain seperator: one time key");
+    let mut digest = hasher.finalize();
+    let mut leaf_seed = Zeroizing::new([0u8; 32]);
+    leaf_seed.copy_from_slice(&digest[0..32]);
+    digest[..].zeroize();
+    keygen(*leaf_seed, pp)
+}
+
+fn hash_leaf(pk: &PublicKey) -> Result<[u8; 32]> {
+    let mut buf: Vec<u8> = vec![];
+    pk.serialize(&mut buf)?;
+    let mut hasher = Sha512::new();
+    hasher.update([buf.as_slice(), "domain seperator: merkle leaf".as_ref()].concat());
+    let digest = hasher.finalize();
+    let mut res = [0u8; 32];
+    res.copy_from_slice(&digest[0..32]);
+    Ok(res)
+}
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha512::new();
+    hasher.update(
+        [
+            left.as_ref(),
+            right.as_ref(),
+            "domain seperator: merkle node".as_ref(),
+        ]
+        .concat(),
+    );
+    let digest = hasher.finalize();
+    let mut res = [0u8; 32];
+    res.copy_from_slice(&digest[0..32]);
+    res
+}
+
+/// build the Merkle tree of all one time public keys derived from the seed
+fn build_tree(seed: &[u8; 32], height: usize, pp: &Param) -> Result<Vec<[u8; 32]>> {
+    let leaves = 1 << height;
+    let mut nodes = vec![[0u8; 32]; leaves << 1];
+    for i in 0..leaves {
+        let (pk, _sk) = leaf_keygen(seed, i, pp)?;
+        nodes[leaves + i] = hash_leaf(&pk)?;
+    }
+    for i in (1..leaves).rev() {
+        nodes[i] = hash_node(&nodes[i << 1], &nodes[(i << 1) + 1]);
+    }
+    Ok(nodes)
+}
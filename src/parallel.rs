@@ -0,0 +1,58 @@
+// helpers to run independent computations on all cores with the `parallel`
+// feature, and sequentially without it; the results are the same either way
+//
+// the bounds do not depend on the feature, so that code that builds without
+// it also builds with it
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// number of computations that run at the same time
+pub(crate) fn threads() -> usize {
+    #[cfg(feature = "parallel")]
+    {
+        rayon::current_num_threads()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        1
+    }
+}
+
+/// apply f to all elements of a, in order
+pub(crate) fn map<T, U, F>(a: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        a.par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        a.iter().map(f).collect()
+    }
+}
+
+/// apply f to all pairs of elements of a and b, in order
+pub(crate) fn zip_map<T, U, V, F>(a: &[T], b: &[U], f: F) -> Vec<V>
+where
+    T: Sync,
+    U: Sync,
+    V: Send,
+    F: Fn(&T, &U) -> V + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    {
+        a.par_iter()
+            .zip(b.par_iter())
+            .map(|(x, y)| f(x, y))
+            .collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        a.iter().zip(b.iter()).map(|(x, y)| f(x, y)).collect()
+    }
+}
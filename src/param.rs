@@ -1,16 +1,18 @@
-use crate::poly::PolyArith;
+use crate::poly::{PolyArith, UniformSample};
 use crate::poly256::Poly256;
-use crate::serde::Serdes;
 use rand::{CryptoRng, RngCore};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
 use sha2::{Digest, Sha512};
+use std::fmt;
+use std::marker::PhantomData;
 /// P is the modulus for `B part`
-pub const P: i64 = 2_097_169;
+pub const P: i32 = 2_097_169;
 
 /// Q is the modulus for `A part`
-pub const Q: i64 = 100_679_681;
+pub const Q: i32 = 100_679_681;
 
 /// R is a root s.t. (x^32+R) divides (x^256+1) mod P
-pub const R: i64 = 852_368;
+pub const R: i32 = 852_368;
 
 /// R_BASE is [(-R)^0, (-R)^1, (-R)^2, (-R)^3, (-R)^4, (-R)^5, (-R)^6, (-R)^7] mod p
 /// 0 1
@@ -21,7 +23,7 @@ pub const R: i64 = 852_368;
 /// 5 1017123
 /// 6 275829
 /// 7 1609180
-pub const R_BASE: [i64; 8] = [
+pub const R_BASE: [i32; 8] = [
     1, 1_244_801, 562_078, 957_346, 1_556_910, 1_017_123, 275_829, 1_609_180,
 ];
 
@@ -37,50 +39,219 @@ pub const Q_RS_RANGE: u32 = 4_228_546_602;
 /// then it produces a uniform value within [0,P)
 pub const P_RS_RANGE: u32 = 4_292_904_943;
 
-/// range for Y
-pub const BETA: i64 = 89_856;
-pub const BETA_M2_P1: u32 = 179_703;
-pub const BETA_M_KAPPA: i64 = 89817;
+/// range for Y, for the `Standard` set
+pub const BETA: i32 = 89_856;
+pub const BETA_M2_P1: u32 = 179_713;
+pub const BETA_M_KAPPA: i32 = 89817;
 
 /// BETA_RS_RANGE: rejection sampling range for beta
 /// BETA_RS_RANGE = 2^32//BETA_M2_P1 * BETA_M2_P1
 /// if a random 32 bits integer is smaller than BETA_RS_RANGE
 /// then it produces a uniform value within [-beta,beta]
-pub const BETA_RS_RANGE: u32 = 4_294_901_700;
+pub const BETA_RS_RANGE: u32 = 4_294_960_987;
 
-/// number of non-zero coefficients in challenge
+/// number of non-zero coefficients in challenge, for the `Standard` set
 pub const KAPPA: usize = 39;
 
-// we should actually reduce this to 864 with a better encoder
-pub const Q_POLY_LEN: usize = 896;
-// we should actually reduce this to 672 with a better encoder
-pub const P_POLY_LEN: usize = 704;
+/// number of bits of a coefficient mod Q, i.e., ceil(log2 Q) = 27
+pub const Q_BITS: usize = (32 - (Q as u32 - 1).leading_zeros()) as usize;
 
-/// the param is a 4*9 matrix of polynomials
+/// number of bits of a coefficient mod P, i.e., ceil(log2 P) = 22
+pub const P_BITS: usize = (32 - (P as u32 - 1).leading_zeros()) as usize;
+
+/// number of bytes of an encoded polynomial mod Q
+pub const Q_POLY_LEN: usize = 256 * Q_BITS / 8;
+
+/// number of bytes of an encoded polynomial mod P; as P = 2^21 + 17, this
+/// is 32 * 22 / 8 = 88 rather than 32 * 21 / 8 = 84 bytes
+pub const P_POLY_LEN: usize = 32 * P_BITS / 8;
+
+/// number of bytes of an encoded trinary polynomial
+pub const TRINARY_POLY_LEN: usize = 256 * 2 / 8;
+
+/// a parameter set of the LB-VRF scheme
+///
+/// all sets share the rings Z_q[x]/(x^256+1) and Z_p[x]/(x^32+R) above, which
+/// the NTT and the reductions are specialised for; a set fixes the dimensions
+/// of the matrix, the challenge weight and the range of y; the remaining
+/// constants are derived from those.
+pub trait ParameterSet: Clone + Copy + fmt::Debug + PartialEq + Send + Sync + 'static {
+    /// name of the set, for display only
+    const NAME: &'static str;
+
+    /// identifier of the set in an envelope, see `envelope`
+    const ID: u8;
+
+    /// number of rows of the matrix, i.e., the length of the public key
+    const K: usize;
+
+    /// number of columns of the matrix, i.e., the length of the secret key and of z
+    const L: usize;
+
+    /// number of non-zero coefficients in challenge
+    const KAPPA: usize;
+
+    /// range for Y
+    const BETA: i32;
+
+    /// 2 * BETA + 1
+    const BETA_M2_P1: u32 = 2 * Self::BETA as u32 + 1;
+
+    /// the norm bound of z; |c * s| is at most KAPPA for a trinary s
+    const BETA_M_KAPPA: i32 = Self::BETA - Self::KAPPA as i32;
+
+    /// BETA_RS_RANGE = 2^32//BETA_M2_P1 * BETA_M2_P1
+    const BETA_RS_RANGE: u32 = u32::MAX / Self::BETA_M2_P1 * Self::BETA_M2_P1;
+
+    /// number of bits to encode a coefficient of z, lifted to [0, 2 * BETA]
+    const BETA_BITS: usize = (32 - (2 * Self::BETA as u32).leading_zeros()) as usize;
+
+    /// number of bytes to encode a challenge: a bitmap of the positions of
+    /// the non-zero coefficients, followed by their signs
+    const CHALLENGE_LEN: usize = 32 + Self::KAPPA.div_ceil(8);
+
+    /// number of bytes of an encoded public key: the param digest and t
+    const PK_LEN: usize = 32 + Self::K * Q_POLY_LEN;
+
+    /// number of bytes of an encoded secret key
+    const SK_LEN: usize = Self::L * TRINARY_POLY_LEN;
+
+    /// number of bytes of an encoded proof
+    const PROOF_LEN: usize = Self::L * 256 * Self::BETA_BITS / 8 + Self::CHALLENGE_LEN + P_POLY_LEN;
+}
+
+/// the original parameters: a 4*9 matrix and challenges of weight 39
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Standard;
+
+impl ParameterSet for Standard {
+    const NAME: &'static str = "standard";
+    const ID: u8 = 1;
+    const K: usize = 4;
+    const L: usize = 9;
+    const KAPPA: usize = KAPPA;
+    const BETA: i32 = BETA;
+}
+
+/// a 5*11 matrix and challenges of weight 60, see `scripts/cal_cs_tail.sage`;
+/// as for the standard set, BETA = KAPPA * 256 * L so that a proof is
+/// accepted with probability about 1/e
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Param {
-    pub matrix: [[Poly256; 9]; 4],
+pub struct HighSecurity;
+
+impl ParameterSet for HighSecurity {
+    const NAME: &'static str = "high security";
+    const ID: u8 = 2;
+    const K: usize = 5;
+    const L: usize = 11;
+    const KAPPA: usize = 60;
+    const BETA: i32 = 168_960;
+}
+
+/// the param is a K*L matrix of polynomials
+#[derive(Clone, Debug, PartialEq)]
+pub struct Param<S: ParameterSet = Standard> {
+    pub matrix: Vec<Vec<Poly256>>,
     pub digest: [u8; 32],
+    /// the matrix in NTT domain, precomputed once
+    pub(crate) matrix_ntt: Vec<Vec<Poly256>>,
+    /// the public seed the matrix is expanded from, if any
+    pub(crate) seed: Option<[u8; 32]>,
+    pub(crate) set: PhantomData<S>,
 }
 
-impl Param {
+impl<S: ParameterSet> Param<S> {
+    /// sample a fresh matrix from the rng; the whole matrix
+    /// needs to be stored and distributed
     pub fn init<R: RngCore + CryptoRng + ?Sized>(mut rng: &mut R) -> Self {
-        let mut res = Self {
-            matrix: [[Poly256::zero(); 9]; 4],
-            digest: [0; 32],
-        };
-        let mut buf: Vec<u8> = vec![];
-        for e in res.matrix.iter_mut() {
+        let mut matrix = vec![vec![Poly256::zero(); S::L]; S::K];
+        for e in matrix.iter_mut() {
             for f in e.iter_mut() {
                 *f = Poly256::uniform_random(&mut rng);
-                // todo: handle error
-                (*f).serialize(&mut buf).unwrap();
             }
         }
+        let digest = matrix_digest(&matrix);
+        Self::from_parts(matrix, digest, None)
+    }
+
+    /// deterministically expand the matrix from a public seed;
+    /// only the seed needs to be stored and distributed, and anyone can
+    /// re-expand it to check that the matrix is "nothing up my sleeve"
+    ///
+    /// the entries and the digest depend on the parameter set, so that the
+    /// params of different sets are unrelated even for the same seed
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let set = [S::ID, S::K as u8, S::L as u8];
+        let mut matrix = vec![vec![Poly256::zero(); S::L]; S::K];
+        for (i, e) in matrix.iter_mut().enumerate() {
+            for (j, f) in e.iter_mut().enumerate() {
+                let mut hasher = Sha512::new();
+                hasher.update(seed);
+                hasher.update(set);
+                hasher.update([i as u8, j as u8]);
+                hasher.update("domain seperator: expand matrix");
+                let digest = hasher.finalize();
+                let mut entry_seed = [0u8; 32];
+                entry_seed.copy_from_slice(&digest[0..32]);
+                let mut rng = ChaCha20Rng::from_seed(entry_seed);
+                *f = Poly256::uniform_random(&mut rng);
+            }
+        }
+
+        // the digest is bound to the seed and the set
         let mut hasher = Sha512::new();
-        hasher.update(buf);
-        let digest = hasher.finalize();
-        res.digest.copy_from_slice(&digest[0..32]);
-        res
+        hasher.update(seed);
+        hasher.update(set);
+        hasher.update("domain seperator: param digest");
+        let hash = hasher.finalize();
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(&hash[0..32]);
+
+        Self::from_parts(matrix, digest, Some(seed))
+    }
+
+    /// the public seed the matrix is expanded from,
+    /// or None if the matrix was sampled with `Param::init`
+    pub fn seed(&self) -> Option<[u8; 32]> {
+        self.seed
+    }
+
+    pub(crate) fn from_parts(
+        matrix: Vec<Vec<Poly256>>,
+        digest: [u8; 32],
+        seed: Option<[u8; 32]>,
+    ) -> Self {
+        let matrix_ntt = matrix_to_ntt(&matrix);
+        Self {
+            matrix,
+            digest,
+            matrix_ntt,
+            seed,
+            set: PhantomData,
+        }
     }
 }
+
+/// digest of a matrix that is not expanded from a seed
+pub(crate) fn matrix_digest(matrix: &[Vec<Poly256>]) -> [u8; 32] {
+    let mut hasher = Sha512::new();
+    for e in matrix.iter() {
+        for f in e.iter() {
+            for g in f.coeff.iter() {
+                hasher.update((*g as u32).to_be_bytes());
+            }
+        }
+    }
+    let hash = hasher.finalize();
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&hash[0..32]);
+    digest
+}
+
+/// convert each entry of the matrix into the NTT domain
+pub(crate) fn matrix_to_ntt(matrix: &[Vec<Poly256>]) -> Vec<Vec<Poly256>> {
+    matrix
+        .iter()
+        .map(|e| e.iter().map(|f| f.ntt()).collect())
+        .collect()
+}
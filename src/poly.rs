@@ -7,38 +7,43 @@ use rand::{CryptoRng, RngCore};
 //     modulus: u32,
 // }
 
-pub trait PolyArith {
+/// ring arithmetic over Z_m[x]/(x^n + r)
+pub trait PolyArith: Sized {
     const DEGREE: usize = 0;
-    const MODULUS: i64 = 0;
+    const MODULUS: i32 = 0;
 
     // arith
     fn add(a: &Self, b: &Self) -> Self;
-    fn add_assign(&mut self, b: &Self)
-    where
-        Self: std::marker::Sized,
-    {
+    fn add_assign(&mut self, b: &Self) {
         *self = Self::add(self, b);
     }
 
     fn sub(a: &Self, b: &Self) -> Self;
-    fn sub_assign(&mut self, b: &Self)
-    where
-        Self: std::marker::Sized,
-    {
+    fn sub_assign(&mut self, b: &Self) {
         *self = Self::sub(self, b);
     }
 
     fn mul(a: &Self, b: &Self) -> Self;
-    fn mul_assign(&mut self, b: &Self)
-    where
-        Self: std::marker::Sized,
-    {
+    fn mul_assign(&mut self, b: &Self) {
         *self = Self::mul(self, b);
     }
 
-    fn mul_trinary(a: &Self, trinary: &Self) -> Self;
     fn mul_karatsuba(a: &Self, b: &Self) -> Self;
 
+    /// inner product of two vectors of polynomials; the result is normalized.
+    /// rings with a faster multiplication override this method
+    fn inner_product(a: &[Self], b: &[Self]) -> Self {
+        if a.len() != b.len() {
+            panic!("inner product: length do not match");
+        }
+        let mut res = Self::zero();
+        for (x, y) in a.iter().zip(b.iter()) {
+            res.add_assign(&Self::mul(x, y));
+        }
+        res.normalized();
+        res
+    }
+
     // lift the coefficients to [0, q-1)
     fn normalized(&mut self);
 
@@ -47,12 +52,36 @@ pub trait PolyArith {
 
     // assign
     fn zero() -> Self;
+}
 
+/// sampling of polynomials with uniform coefficients modulo the ring modulus
+pub trait UniformSample: PolyArith {
     // random polynomials modulo Q
     fn uniform_random<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> Self;
+}
 
+/// sampling of polynomials with short coefficients
+pub trait ShortSample: PolyArith {
     // random polynomials modulus beta
     fn rand_mod_beta<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> Self;
 
     fn rand_trinary<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> Self;
 }
+
+/// multiplication by a polynomial with coefficients in {-1, 0, 1}
+pub trait TrinaryMul: PolyArith {
+    fn mul_trinary(a: &Self, trinary: &Self) -> Self;
+
+    /// inner product where the second vector is trinary; the result is normalized
+    fn inner_product_trinary(a: &[Self], trinary: &[Self]) -> Self {
+        if a.len() != trinary.len() {
+            panic!("inner product: length do not match");
+        }
+        let mut res = Self::zero();
+        for (x, y) in a.iter().zip(trinary.iter()) {
+            res.add_assign(&Self::mul_trinary(x, y));
+        }
+        res.normalized();
+        res
+    }
+}
@@ -1,126 +1,123 @@
 // this file implements neccessary arithmetics over Z_q[x]/(x^256 + 1)
 
+#[cfg(target_arch = "x86_64")]
+use crate::avx2;
+use crate::ct;
+use crate::ntt::{forward_ntt, reverse_ntt};
 use crate::param::{BETA, BETA_M2_P1, BETA_RS_RANGE};
 use crate::param::{Q, Q_RS_RANGE};
-use crate::poly::PolyArith;
+use crate::poly::{PolyArith, ShortSample, TrinaryMul, UniformSample};
+use crate::reduce::reduce_q;
 use rand::{CryptoRng, RngCore};
 use std::fmt;
-#[derive(Clone, Copy)]
+use zeroize::Zeroize;
+#[derive(Clone, Copy, Zeroize)]
 pub struct Poly256 {
-    pub coeff: [i64; 256],
+    pub coeff: [i32; 256],
 }
 
 impl PolyArith for Poly256 {
     const DEGREE: usize = 256;
-    const MODULUS: i64 = Q;
+    const MODULUS: i32 = Q;
 
     fn add(a: &Self, b: &Self) -> Self {
-        let mut res = [0i64; Self::DEGREE];
+        #[cfg(target_arch = "x86_64")]
+        {
+            if avx2::enabled() {
+                let mut res = Self::zero();
+                unsafe { avx2::add(a, b, &mut res) };
+                return res;
+            }
+        }
+        let mut res = [0i32; Self::DEGREE];
         for (i, e) in res.iter_mut().enumerate() {
-            *e = (a.coeff[i] + b.coeff[i]) % Self::MODULUS;
+            *e = reduce_q(a.coeff[i] as i64 + b.coeff[i] as i64);
         }
         Poly256 { coeff: res }
     }
 
     fn sub(a: &Self, b: &Self) -> Self {
-        let mut res = [0i64; 256];
+        #[cfg(target_arch = "x86_64")]
+        {
+            if avx2::enabled() {
+                let mut res = Self::zero();
+                unsafe { avx2::sub(a, b, &mut res) };
+                return res;
+            }
+        }
+        let mut res = [0i32; 256];
         for (i, e) in res.iter_mut().enumerate() {
-            *e = (a.coeff[i] + (Self::MODULUS << 1) - b.coeff[i]) % Self::MODULUS;
+            *e = reduce_q(a.coeff[i] as i64 - b.coeff[i] as i64);
         }
         Poly256 { coeff: res }
     }
 
     fn mul(a: &Self, b: &Self) -> Self {
-        // todo: implement NTT
-        school_book(a, b)
-    }
-    fn mul_trinary(a: &Self, trinary: &Self) -> Self {
-        let mut buf = [0i64; 512];
-        for (i, e) in trinary.coeff.iter().enumerate() {
-            if *e == 1 {
-                for (j, f) in a.coeff.iter().enumerate() {
-                    buf[i + j] += *f;
-                }
-            }
-            if *e == -1 {
-                for (j, f) in a.coeff.iter().enumerate() {
-                    buf[i + j] -= *f;
-                }
-            }
-        }
-        for i in 0..256 {
-            buf[i] = (buf[i] + (Q << 1) - buf[i + 256]) % Q
-        }
-        let mut res = [0i64; 256];
-        res.copy_from_slice(&buf[0..256]);
-        Poly256 { coeff: res }
+        pointwise_mul(&a.ntt(), &b.ntt()).inverse_ntt()
     }
 
+    fn inner_product(a: &[Self], b: &[Self]) -> Self {
+        poly256_inner_product(a, b)
+    }
     fn mul_karatsuba(a: &Self, b: &Self) -> Self {
         // the following code uses karatsuba -- it is somehow slower than school_book
         let mut c = [0i64; Self::DEGREE << 1];
-        karatsuba(&a.coeff, &b.coeff, &mut c, Self::DEGREE);
-        let mut res = [0i64; Self::DEGREE];
-        res.copy_from_slice(
-            &(0..Self::DEGREE)
-                .map(|i| c[i] - c[Self::DEGREE + i])
-                .collect::<Vec<i64>>(),
-        );
-        let mut rt = Self { coeff: res };
-        rt.normalized();
-        rt
+        let a: Vec<i64> = a.coeff.iter().map(|x| *x as i64).collect();
+        let b: Vec<i64> = b.coeff.iter().map(|x| *x as i64).collect();
+        karatsuba(&a, &b, &mut c, Self::DEGREE);
+        let mut res = [0i32; Self::DEGREE];
+        for (i, e) in res.iter_mut().enumerate() {
+            *e = reduce_q(c[i] - c[Self::DEGREE + i]);
+        }
+        Self { coeff: res }
     }
 
     // assign
     fn zero() -> Self {
         Poly256 {
-            coeff: [0i64; Self::DEGREE],
+            coeff: [0i32; Self::DEGREE],
         }
     }
 
     fn normalized(&mut self) {
         for e in self.coeff.iter_mut() {
-            (*e) = (*e + (Self::MODULUS << 1)) % Self::MODULUS;
+            (*e) = reduce_q(*e as i64);
         }
     }
 
     fn centered(&mut self) {
-        self.normalized();
         for e in self.coeff.iter_mut() {
-            if *e << 1 > Self::MODULUS {
-                *e -= Self::MODULUS;
-            }
+            (*e) = ct::center(reduce_q(*e as i64), Self::MODULUS);
         }
     }
+}
+
+impl UniformSample for Poly256 {
     // random polynomials modulo Q
     fn uniform_random<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> Self {
-        let mut coeff = [0i64; Self::DEGREE];
-        for e in &mut coeff.iter_mut() {
+        let mut coeff = [0i32; Self::DEGREE];
+        for e in coeff.iter_mut() {
             let mut tmp = rng.next_u32();
-            while tmp > Q_RS_RANGE {
+            while tmp >= Q_RS_RANGE {
                 tmp = rng.next_u32();
             }
-            *e = (tmp % Self::MODULUS as u32) as i64;
+            *e = (tmp % Self::MODULUS as u32) as i32;
         }
         Poly256 { coeff }
     }
+}
 
+impl ShortSample for Poly256 {
     // random polynomials modulus beta
     fn rand_mod_beta<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> Self {
-        let mut coeff = [0i64; Self::DEGREE];
-        for e in &mut coeff.iter_mut() {
-            let mut tmp = rng.next_u32();
-            while tmp > BETA_RS_RANGE {
-                tmp = rng.next_u32();
-            }
-            tmp %= BETA_M2_P1;
-            *e = (tmp as i32 - (BETA as i32)) as i64;
-        }
-        Poly256 { coeff }
+        rand_mod_bound(rng, BETA, BETA_M2_P1, BETA_RS_RANGE)
     }
 
+    // the loop below rejects the pattern 0b11; the number of rejections
+    // only depends on the rejected bits, and is independent of the
+    // coefficients that are kept
     fn rand_trinary<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> Self {
-        let mut coeff = [0i64; Self::DEGREE];
+        let mut coeff = [0i32; Self::DEGREE];
         let mut tmp = rng.next_u64();
         let mut ct = 0;
         let mut cur;
@@ -137,47 +134,139 @@ impl PolyArith for Poly256 {
                     break;
                 }
             }
-            *e = cur as i64 - 1;
+            *e = cur as i32 - 1;
         }
         Poly256 { coeff }
     }
 }
 
-pub(crate) fn poly256_inner_product(a: &[Poly256], b: &[Poly256]) -> Poly256 {
+/// random polynomial with coefficients uniform in [-bound, range - bound);
+/// rs_range = 2^32//range * range is the rejection sampling range
+pub(crate) fn rand_mod_bound<R: RngCore + CryptoRng + ?Sized>(
+    rng: &mut R,
+    bound: i32,
+    range: u32,
+    rs_range: u32,
+) -> Poly256 {
+    let mut coeff = [0i32; Poly256::DEGREE];
+    for e in coeff.iter_mut() {
+        let mut tmp = rng.next_u32();
+        while tmp >= rs_range {
+            tmp = rng.next_u32();
+        }
+        tmp %= range;
+        *e = tmp as i32 - bound;
+    }
+    Poly256 { coeff }
+}
+
+impl TrinaryMul for Poly256 {
+    // both inputs may be secret, so we do not branch on the trinary
+    // coefficients and multiply by them instead
+    fn mul_trinary(a: &Self, trinary: &Self) -> Self {
+        let mut buf = [0i64; 512];
+        let mut res = [0i32; 256];
+        for (i, e) in trinary.coeff.iter().enumerate() {
+            for (j, f) in a.coeff.iter().enumerate() {
+                buf[i + j] += (*e * *f) as i64;
+            }
+        }
+        for i in 0..256 {
+            res[i] = reduce_q(buf[i] - buf[i + 256])
+        }
+        // the trinary polynomial is often a secret
+        buf.zeroize();
+        Poly256 { coeff: res }
+    }
+}
+
+impl Poly256 {
+    /// convert a polynomial into the NTT domain
+    pub fn ntt(&self) -> Self {
+        let mut res = *self;
+        forward_ntt(&mut res.coeff);
+        res
+    }
+
+    /// convert a polynomial in the NTT domain back into a normal one
+    pub fn inverse_ntt(&self) -> Self {
+        let mut res = *self;
+        reverse_ntt(&mut res.coeff);
+        res
+    }
+}
+
+/// inner product of two vectors of polynomials; the result is normalized
+pub fn poly256_inner_product(a: &[Poly256], b: &[Poly256]) -> Poly256 {
     if a.len() != b.len() {
         panic!("inner product: length do not match");
     }
-    let mut res = Poly256::zero();
-    for i in 0..a.len() {
-        res.add_assign(&Poly256::mul(&a[i], &b[i]));
-    }
-    res.normalized();
-    res
+    let a_ntt: Vec<Poly256> = a.iter().map(|x| x.ntt()).collect();
+    let b_ntt: Vec<Poly256> = b.iter().map(|x| x.ntt()).collect();
+    poly256_inner_product_ntt(&a_ntt, &b_ntt)
+}
+
+/// inner product of two vectors of polynomials that are both in the NTT domain;
+/// the result is converted back into a normal, normalized polynomial
+pub fn poly256_inner_product_ntt(a: &[Poly256], b: &[Poly256]) -> Poly256 {
+    poly256_inner_product_ntt_domain(a, b).inverse_ntt()
 }
 
-pub(crate) fn poly256_inner_product_trinary(a: &[Poly256], b: &[Poly256]) -> Poly256 {
+/// inner product of two vectors of polynomials that are both in the NTT domain;
+/// the result stays in the NTT domain
+pub(crate) fn poly256_inner_product_ntt_domain(a: &[Poly256], b: &[Poly256]) -> Poly256 {
     if a.len() != b.len() {
         panic!("inner product: length do not match");
     }
+    #[cfg(target_arch = "x86_64")]
+    {
+        if avx2::enabled() {
+            let mut res = Poly256::zero();
+            unsafe { avx2::inner_product(a, b, &mut res) };
+            return res;
+        }
+    }
+    // the sum of 9 products of two elements in [0, q) fits in 63 bits
+    let mut buf = [0i64; 256];
+    for (x, y) in a.iter().zip(b.iter()) {
+        for (i, e) in buf.iter_mut().enumerate() {
+            *e += x.coeff[i] as i64 * y.coeff[i] as i64;
+        }
+    }
     let mut res = Poly256::zero();
-    for i in 0..a.len() {
-        res.add_assign(&Poly256::mul_trinary(&a[i], &b[i]));
+    for (i, e) in res.coeff.iter_mut().enumerate() {
+        *e = reduce_q(buf[i]);
     }
-    res.normalized();
     res
 }
 
-#[allow(dead_code)]
-pub(crate) fn school_book(a: &Poly256, b: &Poly256) -> Poly256 {
+/// coefficient-wise product of two polynomials in the NTT domain
+pub(crate) fn pointwise_mul(a: &Poly256, b: &Poly256) -> Poly256 {
+    let mut res = Poly256::zero();
+    #[cfg(target_arch = "x86_64")]
+    {
+        if avx2::enabled() {
+            unsafe { avx2::pointwise_mul(a, b, &mut res) };
+            return res;
+        }
+    }
+    for (i, e) in res.coeff.iter_mut().enumerate() {
+        *e = reduce_q(a.coeff[i] as i64 * b.coeff[i] as i64);
+    }
+    res
+}
+
+/// reference quadratic multiplication; the result is normalized
+pub fn school_book(a: &Poly256, b: &Poly256) -> Poly256 {
     let mut res = [0i64; Poly256::DEGREE << 1];
     let mut array = [0; Poly256::DEGREE];
     for i in 0..Poly256::DEGREE {
         for j in 0..Poly256::DEGREE {
-            res[i + j] += (a.coeff[i] as i64) * (b.coeff[j] as i64);
+            res[i + j] += a.coeff[i] as i64 * b.coeff[j] as i64;
         }
     }
     for i in 0..Poly256::DEGREE {
-        array[i] = (res[i] + Q - res[i + Poly256::DEGREE]) % Q;
+        array[i] = reduce_q(res[i] - res[i + Poly256::DEGREE]);
     }
     Poly256 { coeff: array }
 }
@@ -224,10 +313,10 @@ pub(crate) fn karatsuba(a: &[i64], b: &[i64], c: &mut [i64], n: usize) {
     let a0b1_p_a1b0: Vec<i64> = (0..n).map(|i| one[i] - zero[i] - infinity[i]).collect();
     // c = a0b0 + (a0b1 + a1b0)x + a1b1 x^2
     for i in 0..size {
-        c[i] = zero[i] % Q;
-        c[i + size] = (zero[i + size] + a0b1_p_a1b0[i]) % Q;
-        c[i + n] = (a0b1_p_a1b0[i + size] + infinity[i]) % Q;
-        c[i + size * 3] = infinity[i + size] % Q;
+        c[i] = reduce_q(zero[i]) as i64;
+        c[i + size] = reduce_q(zero[i + size] + a0b1_p_a1b0[i]) as i64;
+        c[i + n] = reduce_q(a0b1_p_a1b0[i + size] + infinity[i]) as i64;
+        c[i + size * 3] = reduce_q(infinity[i + size]) as i64;
     }
 }
 #[allow(dead_code)]
@@ -244,20 +333,20 @@ fn test_karatsuba() {
     let mut rng = rand::thread_rng();
     let mut a = [0i64; 64];
     for e in a.iter_mut() {
-        *e = rng.next_u32() as i64 % Q;
+        *e = rng.next_u32() as i64 % Q as i64;
     }
     let mut b = [0i64; 64];
     for e in b.iter_mut() {
-        *e = rng.next_u32() as i64 % Q;
+        *e = rng.next_u32() as i64 % Q as i64;
     }
     let mut c1 = [0i64; 128];
-    let mut c2 = c1.clone();
+    let mut c2 = c1;
 
     karatsuba(&a, &b, &mut c1, 64);
     school_book_without_reduction(&a, &b, &mut c2, 64);
     println!("{:?}", &c1[0..32]);
     for (i, e) in c1.iter().enumerate() {
-        assert_eq!(*e % Q, c2[i] % Q)
+        assert_eq!(*e % Q as i64, c2[i] % Q as i64)
     }
     // assert!(false);
 }
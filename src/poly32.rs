@@ -1,137 +1,250 @@
 // this file implements neccessary arithmetics over Z_p[x]/(x^32 + R)
 
+use crate::ct;
 use crate::param::{P, P_RS_RANGE, R, R_BASE};
-use crate::poly::PolyArith;
+use crate::poly::{PolyArith, UniformSample};
 use crate::poly256::Poly256;
+use crate::reduce::{barrett_reduce_p, caddp, montgomery_reduce_p, reduce_p, to_montgomery_p};
 use rand::{CryptoRng, RngCore};
-use std::convert::From;
+use std::convert::{From, TryInto};
+use zeroize::Zeroize;
 // use std::fmt;
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Zeroize)]
 pub struct Poly32 {
-    pub coeff: [i64; 32],
+    pub coeff: [i32; 32],
 }
 
+// R_BASE in the Montgomery domain
+const R_BASE_MONT: [i32; 8] = {
+    let mut res = [0i32; 8];
+    let mut i = 0;
+    while i < 8 {
+        res[i] = to_montgomery_p(R_BASE[i]);
+        i += 1;
+    }
+    res
+};
+
 impl From<Poly256> for Poly32 {
+    fn from(a: Poly256) -> Self {
+        (&a).into()
+    }
+}
+
+impl From<&Poly256> for Poly32 {
     // converting a ring element over Z_q[x]/(x^256+1)
     // into a ring elemetn over Z_p[x]/(x^32+R)
-    fn from(a: Poly256) -> Self {
-        let mut res = [0i64; 32];
-        res.copy_from_slice(&a.coeff[0..32]);
+    // the coefficients of a are expected to be in (-q, q)
+    fn from(a: &Poly256) -> Self {
+        let mut res = [0i32; 32];
 
         for (i, e) in res.iter_mut().enumerate() {
-            for (j, r) in R_BASE.iter().enumerate().skip(1) {
-                *e += a.coeff[i + (j << 5)] * (*r);
+            let mut tmp = a.coeff[i] as i64;
+            for (j, r) in R_BASE_MONT.iter().enumerate().skip(1) {
+                tmp += montgomery_reduce_p(a.coeff[i + (j << 5)] as i64 * (*r) as i64) as i64;
             }
-            *e %= P;
+            *e = reduce_p(tmp);
         }
 
         Self { coeff: res }
     }
 }
 
+impl Poly32 {
+    /// project a polynomial in the NTT domain of Z_q[x]/(x^256+1)
+    /// into Z_p[x]/(x^32+R)
+    ///
+    /// the projection is defined on the integer coefficients, and the NTT
+    /// is taken modulo q rather than p, so the polynomial is converted back
+    /// first; its coefficients are lifted to (-q/2, q/2), which is the
+    /// representative used for short polynomials such as y and z
+    pub fn from_ntt(a: &Poly256) -> Self {
+        let mut a = a.inverse_ntt();
+        a.centered();
+        (&a).into()
+    }
+}
+
 impl PolyArith for Poly32 {
     const DEGREE: usize = 32;
-    const MODULUS: i64 = P;
+    const MODULUS: i32 = P;
 
     fn add(a: &Self, b: &Self) -> Self {
-        let mut res = [0i64; Self::DEGREE];
+        let mut res = [0i32; Self::DEGREE];
         for (i, e) in res.iter_mut().enumerate() {
-            *e = (a.coeff[i] + b.coeff[i]) % Self::MODULUS;
+            *e = reduce_p(a.coeff[i] as i64 + b.coeff[i] as i64);
         }
         Poly32 { coeff: res }
     }
 
     fn sub(a: &Self, b: &Self) -> Self {
-        let mut res = [0i64; 32];
+        let mut res = [0i32; 32];
         for (i, e) in res.iter_mut().enumerate() {
-            *e = (a.coeff[i] + (Self::MODULUS << 1) - b.coeff[i]) % Self::MODULUS;
+            *e = reduce_p(a.coeff[i] as i64 - b.coeff[i] as i64);
         }
         Poly32 { coeff: res }
     }
 
+    // x^32 + R is irreducible mod p (-R has order 16, and 16 is the largest
+    // power of 2 dividing p - 1), so there is no NTT for this ring
     fn mul(a: &Self, b: &Self) -> Self {
-        // todo: implement NTT
-        school_book(a, b)
+        Self::mul_karatsuba(a, b)
     }
 
-    fn mul_trinary(_a: &Self, _trinary: &Self) -> Self {
-        unimplemented!()
+    fn inner_product(a: &[Self], b: &[Self]) -> Self {
+        poly32_inner_product(a, b)
     }
-    fn mul_karatsuba(_a: &Self, _b: &Self) -> Self {
-        unimplemented!()
+
+    fn mul_karatsuba(a: &Self, b: &Self) -> Self {
+        let mut c = [0i64; Self::DEGREE << 1];
+        mul_acc(a, b, &mut c);
+        reduce_product(&c)
     }
 
     // assign
     fn zero() -> Self {
         Poly32 {
-            coeff: [0i64; Self::DEGREE],
+            coeff: [0i32; Self::DEGREE],
         }
     }
 
     fn normalized(&mut self) {
         for e in self.coeff.iter_mut() {
-            (*e) = (*e + (Self::MODULUS << 1)) % Self::MODULUS;
+            (*e) = reduce_p(*e as i64);
         }
     }
 
     fn centered(&mut self) {
-        self.normalized();
         for e in self.coeff.iter_mut() {
-            if *e << 1 > Self::MODULUS {
-                *e -= Self::MODULUS;
-            }
+            (*e) = ct::center(reduce_p(*e as i64), Self::MODULUS);
         }
     }
-    // random polynomials modulo Q
+}
+
+// short polynomials are never sampled over this ring, so Poly32 does
+// not implement ShortSample nor TrinaryMul
+impl UniformSample for Poly32 {
+    // random polynomials modulo P
     fn uniform_random<R: RngCore + CryptoRng + ?Sized>(rng: &mut R) -> Self {
-        let mut coeff = [0i64; Self::DEGREE];
-        for e in &mut coeff.iter_mut() {
+        let mut coeff = [0i32; Self::DEGREE];
+        for e in coeff.iter_mut() {
             let mut tmp = rng.next_u32();
-            while tmp > P_RS_RANGE {
+            while tmp >= P_RS_RANGE {
                 tmp = rng.next_u32();
             }
-            *e = (tmp % Self::MODULUS as u32) as i64;
+            *e = (tmp % Self::MODULUS as u32) as i32;
         }
         Poly32 { coeff }
     }
-
-    // random polynomials modulus beta
-    fn rand_mod_beta<R: RngCore + CryptoRng + ?Sized>(_rng: &mut R) -> Self {
-        // we will never need to sample a uniform mod beta element
-        // over this ring
-        unimplemented!();
-    }
-
-    fn rand_trinary<R: RngCore + CryptoRng + ?Sized>(_rng: &mut R) -> Self {
-        // we will never need to sample a trinary element
-        // over this ring
-        unimplemented!();
-    }
 }
 
-pub(crate) fn poly32_inner_product(a: &[Poly32], b: &[Poly32]) -> Poly32 {
+/// inner product of two vectors of polynomials; the result is normalized
+pub fn poly32_inner_product(a: &[Poly32], b: &[Poly32]) -> Poly32 {
     if a.len() != b.len() {
         panic!("inner product: length do not match");
     }
+    // the products are accumulated without reduction, up to 16 of them
+    // so that the sum stays within the range of `reduce_product`
     let mut res = Poly32::zero();
-    for i in 0..a.len() {
-        res.add_assign(&Poly32::mul(&a[i], &b[i]));
+    for (x, y) in a.chunks(16).zip(b.chunks(16)) {
+        let mut c = [0i64; Poly32::DEGREE << 1];
+        for (e, f) in x.iter().zip(y.iter()) {
+            mul_acc(e, f, &mut c);
+        }
+        res.add_assign(&reduce_product(&c));
+        c.zeroize();
     }
-    res.normalized();
     res
 }
 
-fn school_book(a: &Poly32, b: &Poly32) -> Poly32 {
+// R * 2^32 mod p
+const R_MONT: i64 = to_montgomery_p(R) as i64;
+
+// c += a * b * 2^32 over the integers; a is converted into the Montgomery
+// domain and b is reduced into (-p, p), so that each coefficient of the
+// product is at most 32 * p^2 < 2^47 in absolute value
+fn mul_acc(a: &Poly32, b: &Poly32, c: &mut [i64; 64]) {
+    let mut x = [0i32; 32];
+    let mut y = [0i32; 32];
+    for i in 0..32 {
+        x[i] = to_montgomery_p(a.coeff[i]);
+        y[i] = barrett_reduce_p(b.coeff[i]);
+    }
+    karatsuba(&x, &y, c);
+    // a or b may be secret
+    x.zeroize();
+    y.zeroize();
+}
+
+// reduce a product in the Montgomery domain, of degree < 64 and with
+// coefficients less than 2^51, modulo (p, x^32 + R); the result is normalized
+fn reduce_product(c: &[i64; 64]) -> Poly32 {
+    let mut res = [0i32; 32];
+    for (i, e) in res.iter_mut().enumerate() {
+        // c[i] * 2^-32 - R * c[i + 32] * 2^-32
+        let u = montgomery_reduce_p(c[i + 32]) as i64;
+        *e = caddp(montgomery_reduce_p(c[i] - u * R_MONT));
+    }
+    Poly32 { coeff: res }
+}
+
+// c += a * b with one level of Karatsuba on the halves of a and b;
+// the coefficients are not reduced, and only the products are widened
+// to 64 bits, which lets the compiler vectorize the multiplications
+fn karatsuba(a: &[i32; 32], b: &[i32; 32], c: &mut [i64; 64]) {
+    // a0 * b0, a1 * b1 and (a0 + a1)(b0 + b1)
+    let mut lo = [0i64; 32];
+    let mut hi = [0i64; 32];
+    let mut mid = [0i64; 32];
+    let mut sa = [0i32; 16];
+    let mut sb = [0i32; 16];
+    for i in 0..16 {
+        sa[i] = a[i] + a[i + 16];
+        sb[i] = b[i] + b[i + 16];
+    }
+    school_book16(
+        a[..16].try_into().unwrap(),
+        b[..16].try_into().unwrap(),
+        &mut lo,
+    );
+    school_book16(
+        a[16..].try_into().unwrap(),
+        b[16..].try_into().unwrap(),
+        &mut hi,
+    );
+    school_book16(&sa, &sb, &mut mid);
+    for i in 0..32 {
+        c[i] += lo[i];
+        c[i + 16] += mid[i] - lo[i] - hi[i];
+        c[i + 32] += hi[i];
+    }
+    lo.zeroize();
+    hi.zeroize();
+    mid.zeroize();
+    sa.zeroize();
+    sb.zeroize();
+}
+
+fn school_book16(a: &[i32; 16], b: &[i32; 16], c: &mut [i64; 32]) {
+    for i in 0..16 {
+        for j in 0..16 {
+            c[i + j] += a[i] as i64 * b[j] as i64;
+        }
+    }
+}
+
+/// reference quadratic multiplication; the result is normalized
+pub fn school_book(a: &Poly32, b: &Poly32) -> Poly32 {
     let mut res = [0i64; Poly32::DEGREE << 1];
     let mut array = [0; Poly32::DEGREE];
     for i in 0..Poly32::DEGREE {
         for j in 0..Poly32::DEGREE {
-            res[i + j] += (a.coeff[i] as i64) * (b.coeff[j] as i64);
+            res[i + j] += a.coeff[i] as i64 * b.coeff[j] as i64;
         }
     }
 
     for i in 0..Poly32::DEGREE {
-        array[i] = (res[i] + (P << 2) - R * (res[i + Poly32::DEGREE] % P)) % P;
+        array[i] = reduce_p(res[i] - R as i64 * reduce_p(res[i + Poly32::DEGREE]) as i64);
     }
     Poly32 { coeff: array }
 }
@@ -0,0 +1,209 @@
+// this file implements Montgomery and Barrett reductions specialised to
+// the two moduli of the scheme, Q = 100_679_681 and P = 2_097_169
+//
+// coefficients are stored as i32; products are formed in i64 and brought
+// back with the routines below:
+//  - Montgomery reduction for products with a precomputed constant
+//    (NTT twiddle factors, CRT basis), with the Montgomery radix 2^32
+//  - 32 bit Barrett reduction for the lazy additions inside the NTT
+//  - 64 bit Barrett reduction into the canonical range [0, m)
+// none of the routines branch on their inputs.
+
+use crate::param::{P, Q};
+
+/// q^-1 mod 2^32, as a signed integer
+pub const Q_INV: i32 = 167_755_777;
+
+/// 2^64 mod q; multiplying by it converts into the Montgomery domain
+pub const Q_MONT2: i32 = 77_063_662;
+
+/// floor(2^64 / q) for the 64 bit Barrett reduction
+pub const Q_BARRETT: i128 = 183_222_114_834;
+
+/// round(2^57 / q) for the 32 bit Barrett reduction
+pub const Q_BARRETT32: i64 = 1_431_422_772;
+
+/// p^-1 mod 2^32, as a signed integer
+pub const P_INV: i32 = -1_798_246_159;
+
+/// 2^64 mod p; multiplying by it converts into the Montgomery domain
+pub const P_MONT2: i32 = 2_087_343;
+
+/// floor(2^64 / p) for the 64 bit Barrett reduction
+pub const P_BARRETT: i128 = 8_796_021_719_617;
+
+/// round(2^52 / p) for the 32 bit Barrett reduction
+pub const P_BARRETT32: i64 = 2_147_466_240;
+
+/// Montgomery reduction modulo q:
+/// input |a| < q * 2^31, output a * 2^-32 mod q in (-q, q)
+#[inline(always)]
+pub const fn montgomery_reduce_q(a: i64) -> i32 {
+    montgomery_reduce(a, Q, Q_INV)
+}
+
+/// Montgomery reduction modulo p:
+/// input |a| < p * 2^31, output a * 2^-32 mod p in (-p, p)
+#[inline(always)]
+pub const fn montgomery_reduce_p(a: i64) -> i32 {
+    montgomery_reduce(a, P, P_INV)
+}
+
+/// convert a into the Montgomery domain modulo q, i.e., a * 2^32 mod q in (-q, q)
+#[inline(always)]
+pub const fn to_montgomery_q(a: i32) -> i32 {
+    montgomery_reduce_q(a as i64 * Q_MONT2 as i64)
+}
+
+/// convert a into the Montgomery domain modulo p, i.e., a * 2^32 mod p in (-p, p)
+#[inline(always)]
+pub const fn to_montgomery_p(a: i32) -> i32 {
+    montgomery_reduce_p(a as i64 * P_MONT2 as i64)
+}
+
+/// 32 bit Barrett reduction modulo q:
+/// input any i32, output a representative in (-q, q)
+#[inline(always)]
+pub const fn barrett_reduce_q(a: i32) -> i32 {
+    barrett_reduce32(a, Q, Q_BARRETT32, 57)
+}
+
+/// 32 bit Barrett reduction modulo p:
+/// input any i32, output a representative in (-p, p)
+#[inline(always)]
+pub const fn barrett_reduce_p(a: i32) -> i32 {
+    barrett_reduce32(a, P, P_BARRETT32, 52)
+}
+
+/// 64 bit Barrett reduction modulo q:
+/// input |a| < 2^63, output the canonical representative in [0, q)
+#[inline(always)]
+pub const fn reduce_q(a: i64) -> i32 {
+    barrett_reduce64(a, Q, Q_BARRETT)
+}
+
+/// 64 bit Barrett reduction modulo p:
+/// input |a| < 2^63, output the canonical representative in [0, p)
+#[inline(always)]
+pub const fn reduce_p(a: i64) -> i32 {
+    barrett_reduce64(a, P, P_BARRETT)
+}
+
+/// map a representative in (-q, q) to [0, q)
+#[inline(always)]
+pub const fn caddq(a: i32) -> i32 {
+    a + (Q & (a >> 31))
+}
+
+/// map a representative in (-p, p) to [0, p)
+#[inline(always)]
+pub const fn caddp(a: i32) -> i32 {
+    a + (P & (a >> 31))
+}
+
+#[inline(always)]
+const fn montgomery_reduce(a: i64, m: i32, m_inv: i32) -> i32 {
+    // t = a * m^-1 mod 2^32, so that a - t * m is divisible by 2^32
+    let t = (a as i32).wrapping_mul(m_inv);
+    ((a - t as i64 * m as i64) >> 32) as i32
+}
+
+#[inline(always)]
+const fn barrett_reduce32(a: i32, m: i32, v: i64, shift: u32) -> i32 {
+    // v is round(2^shift / m) and fits in 31 bits, so that a * v fits in 63 bits;
+    // t is round(a / m), up to an error of one
+    let t = (a as i64 * v + (1 << (shift - 1))) >> shift;
+    (a as i64 - t * m as i64) as i32
+}
+
+#[inline(always)]
+const fn barrett_reduce64(a: i64, m: i32, v: i128) -> i32 {
+    let m = m as i64;
+    // t is floor(a / m), up to an error of one in either direction
+    let t = ((a as i128 * v) >> 64) as i64;
+    // r is in [-m, 2m)
+    let mut r = a - t * m;
+    // r is in [0, 2m)
+    r += m & (r >> 63);
+    // r is in [0, m)
+    r -= m & !((r - m) >> 63);
+    r as i32
+}
+
+#[test]
+fn test_reduce() {
+    use rand::RngCore;
+    let mut rng = rand::thread_rng();
+    // the constants
+    assert_eq!((Q as i64 * Q_INV as i64) as i32, 1);
+    assert_eq!((P as i64 * P_INV as i64) as i32, 1);
+    assert_eq!(Q_MONT2 as i128, (1i128 << 64) % Q as i128);
+    assert_eq!(P_MONT2 as i128, (1i128 << 64) % P as i128);
+    assert_eq!(Q_BARRETT, (1i128 << 64) / Q as i128);
+    assert_eq!(P_BARRETT, (1i128 << 64) / P as i128);
+    assert_eq!(Q_BARRETT32, ((1i64 << 57) + (Q as i64 >> 1)) / Q as i64);
+    assert_eq!(P_BARRETT32, ((1i64 << 52) + (P as i64 >> 1)) / P as i64);
+
+    let mut inputs = vec![
+        0,
+        1,
+        -1,
+        i32::MAX,
+        i32::MIN,
+        Q,
+        -Q,
+        P,
+        -P,
+        Q - 1,
+        1 - Q,
+        P - 1,
+        1 - P,
+    ];
+    for _ in 0..10000 {
+        inputs.push(rng.next_u32() as i32);
+    }
+    for a in inputs.iter() {
+        let a = *a;
+        let r = barrett_reduce_q(a);
+        assert!(r > -Q && r < Q);
+        assert_eq!((r as i64 - a as i64).rem_euclid(Q as i64), 0);
+        let r = barrett_reduce_p(a);
+        assert!(r > -P && r < P);
+        assert_eq!((r as i64 - a as i64).rem_euclid(P as i64), 0);
+
+        // a * b with |b| < m stays within the Montgomery input range
+        let b = rng.next_u32() as i64 % Q as i64;
+        let r = montgomery_reduce_q(a as i64 * b);
+        assert!(r > -Q && r < Q);
+        assert_eq!(
+            ((r as i128) << 32).rem_euclid(Q as i128),
+            (a as i128 * b as i128).rem_euclid(Q as i128)
+        );
+        let b = rng.next_u32() as i64 % P as i64;
+        let r = montgomery_reduce_p(a as i64 * b);
+        assert!(r > -P && r < P);
+        assert_eq!(
+            ((r as i128) << 32).rem_euclid(P as i128),
+            (a as i128 * b as i128).rem_euclid(P as i128)
+        );
+        let r = montgomery_reduce_q(to_montgomery_q(a) as i64);
+        assert_eq!((r as i64 - a as i64).rem_euclid(Q as i64), 0);
+        let r = montgomery_reduce_p(to_montgomery_p(a) as i64);
+        assert_eq!((r as i64 - a as i64).rem_euclid(P as i64), 0);
+    }
+
+    for _ in 0..10000 {
+        let a = rng.next_u64() as i64 >> 1;
+        assert_eq!(reduce_q(a) as i64, a.rem_euclid(Q as i64));
+        assert_eq!(reduce_p(a) as i64, a.rem_euclid(P as i64));
+        let a = a >> 20;
+        assert_eq!(reduce_q(a) as i64, a.rem_euclid(Q as i64));
+        assert_eq!(reduce_p(a) as i64, a.rem_euclid(P as i64));
+    }
+    for a in [-Q, -1, 0, 1, Q - 1].iter() {
+        assert_eq!(caddq(*a), a.rem_euclid(Q));
+    }
+    for a in [-P, -1, 0, 1, P - 1].iter() {
+        assert_eq!(caddp(*a), a.rem_euclid(P));
+    }
+}
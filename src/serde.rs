@@ -1,12 +1,32 @@
-use crate::keypair::{PublicKey, SecretKey};
+use crate::ecvrf::{string_to_point, ECProof, ECVRFSuite, EC_PROOF_LEN};
+use crate::error::{Error, Result};
+use crate::hybrid::{HybridParam, HybridProof};
+use crate::keypair::{
+    ECPublicKey, ECSecretKey, HybridPublicKey, HybridSecretKey, MTPublicKey, MTSecretKey,
+    PublicKey, SecretKey,
+};
 use crate::lbvrf::{Proof, VRFOutput};
-use crate::param::Param;
-use crate::param::BETA;
+use crate::mtlbvrf::{MTParam, MTProof, MT_MAX_HEIGHT};
+use crate::param::{matrix_digest, Param, ParameterSet, P, P_BITS, Q, Q_BITS};
 use crate::poly::PolyArith;
 use crate::poly256::Poly256;
 use crate::poly32::Poly32;
-use std::io::{Read, Result, Write};
+use std::fmt;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, OnceLock};
+use zeroize::{Zeroize, Zeroizing};
 
+// leading byte of an encoded param
+const PARAM_FULL: u8 = 0;
+const PARAM_SEEDED: u8 = 1;
+
+/// binary encodings of the objects of this crate
+///
+/// the encodings are canonical: `deserialize` rejects any input that
+/// `serialize` does not produce, such as unreduced coefficients, so that an
+/// object has exactly one encoding
 pub trait Serdes {
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()>;
 
@@ -16,10 +36,11 @@ pub trait Serdes {
 }
 
 impl Serdes for Poly32 {
-    // todo: use a more efficient way to (de)serialize poly256
-    // todo: use a different endocing scheme for trinary secrets
+    // the coefficients are written reduced mod p
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
-        pack_mod_p_poly(self, writer)
+        let mut p = *self;
+        p.normalized();
+        pack_mod_p_poly(&p, writer)
     }
 
     fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
@@ -33,39 +54,42 @@ impl Serdes for Poly32 {
 }
 
 impl Serdes for Poly256 {
-    // todo: use a more efficient way to (de)serialize poly256
-    // todo: use a different endocing scheme for trinary secrets
+    // the coefficients are written reduced mod q
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
-        for e in self.coeff.iter() {
-            let tmp = *e as u32;
-            let buf = tmp.to_be_bytes();
-            writer.write_all(&buf)?;
-        }
-        Ok(())
+        let mut p = *self;
+        p.normalized();
+        pack_mod_q_poly(&p, writer)
     }
 
     fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
     where
         Self: std::marker::Sized,
     {
-        let mut coeff = [0i64; 256];
-        let mut buf = [0u8; 4];
-        for e in coeff.iter_mut() {
-            reader.read_exact(&mut buf)?;
-            *e = i32::from_be_bytes(buf) as i64;
-        }
-        Ok(Self { coeff })
+        let mut res = Poly256::zero();
+        unpack_mod_q_poly(&mut res, reader)?;
+        Ok(res)
     }
 }
 
-impl Serdes for Param {
+impl<S: ParameterSet> Serdes for Param<S> {
+    // a param expanded from a seed is encoded as the seed only;
+    // otherwise the whole matrix and its digest are written
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
-        for e in self.matrix.iter() {
-            for f in e {
-                pack_mod_q_poly(f, writer)?;
+        match self.seed {
+            Some(seed) => {
+                writer.write_all(&[PARAM_SEEDED])?;
+                writer.write_all(&seed)?;
+            }
+            None => {
+                writer.write_all(&[PARAM_FULL])?;
+                for e in self.matrix.iter() {
+                    for f in e {
+                        pack_mod_q_poly(f, writer)?;
+                    }
+                }
+                writer.write_all(&self.digest)?;
             }
         }
-        writer.write_all(&self.digest)?;
         Ok(())
     }
 
@@ -73,23 +97,37 @@ impl Serdes for Param {
     where
         Self: std::marker::Sized,
     {
-        let mut res = [[Poly256::zero(); 9]; 4];
-        for e in res.iter_mut() {
-            for f in e.iter_mut() {
-                unpack_mod_q_poly(f, reader)?;
+        let mut mode = [0u8; 1];
+        reader.read_exact(&mut mode)?;
+        match mode[0] {
+            PARAM_SEEDED => {
+                let mut seed = [0u8; 32];
+                reader.read_exact(&mut seed)?;
+                Ok(Param::from_seed(seed))
             }
+            PARAM_FULL => {
+                let mut res = vec![vec![Poly256::zero(); S::L]; S::K];
+                for e in res.iter_mut() {
+                    for f in e.iter_mut() {
+                        unpack_mod_q_poly(f, reader)?;
+                    }
+                }
+                let mut digest = [0u8; 32];
+                reader.read_exact(&mut digest)?;
+                if digest != matrix_digest(&res) {
+                    return Err(Error::MalformedEncoding("param digest mismatch"));
+                }
+                Ok(Param::from_parts(res, digest, None))
+            }
+            _ => Err(Error::MalformedEncoding("unknown param mode")),
         }
-        let mut digest = [0u8; 32];
-        reader.read_exact(&mut digest)?;
-        Ok(Param {
-            matrix: res,
-            digest,
-        })
     }
 }
 
-impl Serdes for PublicKey {
+// the param digest, followed by t
+impl<S: ParameterSet> Serdes for PublicKey<S> {
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.digest)?;
         for e in self.t.iter() {
             pack_mod_q_poly(e, writer)?;
         }
@@ -100,15 +138,21 @@ impl Serdes for PublicKey {
     where
         Self: std::marker::Sized,
     {
-        let mut res = [Poly256::zero(); 4];
-        for e in res.iter_mut() {
+        let mut digest = [0u8; 32];
+        reader.read_exact(&mut digest)?;
+        let mut t = vec![Poly256::zero(); S::K];
+        for e in t.iter_mut() {
             unpack_mod_q_poly(e, reader)?;
         }
-        Ok(PublicKey { t: res })
+        Ok(PublicKey {
+            t,
+            digest,
+            set: PhantomData,
+        })
     }
 }
 
-impl Serdes for SecretKey {
+impl<S: ParameterSet> Serdes for SecretKey<S> {
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
         for e in self.s.iter() {
             pack_trinary(e, writer)?;
@@ -120,21 +164,24 @@ impl Serdes for SecretKey {
     where
         Self: std::marker::Sized,
     {
-        let mut res = [Poly256::zero(); 9];
-        for e in res.iter_mut() {
+        // wiped if the encoding is rejected half way
+        let mut s = Zeroizing::new(vec![Poly256::zero(); S::L]);
+        for e in s.iter_mut() {
             unpack_trinary(e, reader)?;
         }
-        Ok(SecretKey { s: res })
+        Ok(SecretKey {
+            s: std::mem::take(&mut *s),
+            set: PhantomData,
+        })
     }
 }
 
-impl Serdes for Proof {
+impl<S: ParameterSet> Serdes for Proof<S> {
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
         for e in self.z.iter() {
-            pack_mod_beta_poly(e, writer)?;
+            pack_mod_beta_poly(e, S::BETA, S::BETA_BITS, writer)?;
         }
-        // todo: improve this packing method for challenges
-        pack_trinary(&self.c, writer)?;
+        pack_challenge(&self.c, S::KAPPA, writer)?;
         self.v.serialize(writer)?;
         Ok(())
     }
@@ -143,147 +190,538 @@ impl Serdes for Proof {
     where
         Self: std::marker::Sized,
     {
-        let mut z = [Poly256::zero(); 9];
+        let mut z = vec![Poly256::zero(); S::L];
         for e in z.iter_mut() {
-            unpack_mod_beta_poly(e, reader)?;
+            unpack_mod_beta_poly(e, S::BETA, S::BETA_BITS, reader)?;
         }
         let mut c = Poly256::zero();
-        unpack_trinary(&mut c, reader)?;
+        unpack_challenge(&mut c, S::KAPPA, reader)?;
         let v = VRFOutput::deserialize(reader)?;
-        Ok(Proof { z, c, v })
+        Ok(Proof {
+            z,
+            c,
+            v,
+            set: PhantomData,
+        })
     }
 }
 
-fn pack_trinary<W: Write>(p: &Poly256, writer: &mut W) -> Result<()> {
-    for i in 0..64 {
-        let mut tmp = p.coeff[i * 4] + 1;
-        tmp <<= 2;
-        tmp += p.coeff[i * 4 + 1] + 1;
-        tmp <<= 2;
-        tmp += p.coeff[i * 4 + 2] + 1;
-        tmp <<= 2;
-        tmp += p.coeff[i * 4 + 3] + 1;
-        writer.write_all(&[tmp as u8])?;
+impl Serdes for MTParam {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.param.serialize(writer)?;
+        writer.write_all(&[self.height as u8])?;
+        Ok(())
     }
 
-    Ok(())
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let param = Param::deserialize(reader)?;
+        let height = read_height(reader)?;
+        Ok(MTParam { param, height })
+    }
 }
 
-fn unpack_trinary<R: Read>(res: &mut Poly256, reader: &mut R) -> Result<()> {
-    // let mut res = [0i64; 256];
-    let mut buf = [0u8; 64];
-    reader.read_exact(&mut buf)?;
-    for (i, e) in buf.iter_mut().enumerate() {
-        res.coeff[i * 4 + 3] = (*e & 0b11) as i64 - 1;
-        (*e) >>= 2;
-        res.coeff[i * 4 + 2] = (*e & 0b11) as i64 - 1;
-        (*e) >>= 2;
-        res.coeff[i * 4 + 1] = (*e & 0b11) as i64 - 1;
-        (*e) >>= 2;
-        res.coeff[i * 4] = (*e & 0b11) as i64 - 1;
+impl Serdes for MTPublicKey {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.root)?;
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let mut root = [0u8; 32];
+        reader.read_exact(&mut root)?;
+        Ok(MTPublicKey { root })
     }
-    Ok(())
-    // Ok(Poly256 { coeff: res })
 }
 
-fn pack_mod_q_poly<W: Write>(p: &Poly256, writer: &mut W) -> Result<()> {
-    for i in 0..64 {
-        let mut tmp: u128 = p.coeff[i * 4] as u128;
-        tmp <<= 27;
-        tmp += p.coeff[i * 4 + 1] as u128;
-        tmp <<= 27;
-        tmp += p.coeff[i * 4 + 2] as u128;
-        tmp <<= 27;
-        tmp += p.coeff[i * 4 + 3] as u128;
-        writer.write_all(&tmp.to_be_bytes()[2..16])?;
+impl Serdes for MTSecretKey {
+    // the Merkle tree is not serialized; it is rebuilt from the seed
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.seed)?;
+        writer.write_all(&[self.height as u8])?;
+        writer.write_all(&(self.next_leaf() as u32).to_be_bytes())?;
+        Ok(())
     }
 
-    Ok(())
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let mut seed = [0u8; 32];
+        reader.read_exact(&mut seed)?;
+        let height = read_height(reader)?;
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf)?;
+        let next_leaf = u32::from_be_bytes(buf) as usize;
+        if next_leaf > 1 << height {
+            return Err(Error::MalformedEncoding("invalid leaf index"));
+        }
+        Ok(MTSecretKey {
+            seed,
+            height,
+            next_leaf: Arc::new(AtomicUsize::new(next_leaf)),
+            nodes: Arc::new(OnceLock::new()),
+        })
+    }
 }
 
-fn unpack_mod_q_poly<R: Read>(res: &mut Poly256, reader: &mut R) -> Result<()> {
-    for i in 0..64 {
-        let mut buf = [0u8; 16];
-        reader.read_exact(&mut buf[2..16])?;
-
-        let mut tmp = u128::from_be_bytes(buf);
-        res.coeff[i * 4 + 3] = (tmp & 0x07FF_FFFF) as i64;
-        tmp >>= 27;
-        res.coeff[i * 4 + 2] = (tmp & 0x07FF_FFFF) as i64;
-        tmp >>= 27;
-        res.coeff[i * 4 + 1] = (tmp & 0x07FF_FFFF) as i64;
-        tmp >>= 27;
-        res.coeff[i * 4] = tmp as i64;
+impl Serdes for MTProof {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&(self.index as u32).to_be_bytes())?;
+        self.pk.serialize(writer)?;
+        self.proof.serialize(writer)?;
+        writer.write_all(&[self.path.len() as u8])?;
+        for e in self.path.iter() {
+            writer.write_all(e)?;
+        }
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf)?;
+        let index = u32::from_be_bytes(buf) as usize;
+        let pk = PublicKey::deserialize(reader)?;
+        let proof = Proof::deserialize(reader)?;
+        let height = read_height(reader)?;
+        let mut path = vec![[0u8; 32]; height];
+        for e in path.iter_mut() {
+            reader.read_exact(e)?;
+        }
+        Ok(MTProof {
+            index,
+            pk,
+            proof,
+            path,
+        })
     }
-    Ok(())
 }
 
-fn pack_mod_beta_poly<W: Write>(p: &Poly256, writer: &mut W) -> Result<()> {
-    for i in 0..64 {
-        let mut tmp: u128 = (p.coeff[i * 4] + BETA) as u128;
-        tmp <<= 18;
-        tmp += (p.coeff[i * 4 + 1] + BETA) as u128;
-        tmp <<= 18;
-        tmp += (p.coeff[i * 4 + 2] + BETA) as u128;
-        tmp <<= 18;
-        tmp += (p.coeff[i * 4 + 3] + BETA) as u128;
+impl Serdes for ECVRFSuite {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&[self.suite_string()])?;
+        Ok(())
+    }
 
-        writer.write_all(&tmp.to_be_bytes()[7..16])?;
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let mut buf = [0u8; 1];
+        reader.read_exact(&mut buf)?;
+        match ECVRFSuite::from_suite_string(buf[0]) {
+            Some(p) => Ok(p),
+            None => Err(Error::MalformedEncoding("unknown ecvrf suite")),
+        }
     }
-    Ok(())
 }
 
-fn unpack_mod_beta_poly<R: Read>(res: &mut Poly256, reader: &mut R) -> Result<()> {
-    println!();
-    for i in 0..64 {
-        let mut buf = [0u8; 16];
-        reader.read_exact(&mut buf[7..16])?;
-        let mut tmp = u128::from_be_bytes(buf);
-        res.coeff[i * 4 + 3] = (tmp & 0x0003_FFFF) as i64 - BETA;
-        tmp >>= 18;
-        res.coeff[i * 4 + 2] = (tmp & 0x0003_FFFF) as i64 - BETA;
-        tmp >>= 18;
-        res.coeff[i * 4 + 1] = (tmp & 0x0003_FFFF) as i64 - BETA;
-        tmp >>= 18;
-        res.coeff[i * 4] = tmp as i64 - BETA;
+impl Serdes for ECPublicKey {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(self.point.compress().as_bytes())?;
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let mut buf = [0u8; 32];
+        reader.read_exact(&mut buf)?;
+        match string_to_point(&buf) {
+            Some(point) => Ok(ECPublicKey { point }),
+            None => Err(Error::MalformedEncoding("invalid point")),
+        }
+    }
+}
+
+impl Serdes for ECSecretKey {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.seed)?;
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let mut seed = [0u8; 32];
+        reader.read_exact(&mut seed)?;
+        Ok(ECSecretKey { seed })
+    }
+}
+
+impl Serdes for ECProof {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let mut buf = [0u8; EC_PROOF_LEN];
+        reader.read_exact(&mut buf)?;
+        ECProof::from_bytes(&buf)
+    }
+}
+
+impl Serdes for HybridParam {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.ec.serialize(writer)?;
+        self.lb.serialize(writer)?;
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let ec = ECVRFSuite::deserialize(reader)?;
+        let lb = Param::deserialize(reader)?;
+        Ok(HybridParam { ec, lb })
+    }
+}
+
+impl Serdes for HybridPublicKey {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.ec.serialize(writer)?;
+        self.lb.serialize(writer)?;
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let ec = ECPublicKey::deserialize(reader)?;
+        let lb = PublicKey::deserialize(reader)?;
+        Ok(HybridPublicKey { ec, lb })
+    }
+}
+
+impl Serdes for HybridSecretKey {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.ec.serialize(writer)?;
+        self.lb.serialize(writer)?;
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let ec = ECSecretKey::deserialize(reader)?;
+        let lb = SecretKey::deserialize(reader)?;
+        Ok(HybridSecretKey { ec, lb })
+    }
+}
+
+impl Serdes for HybridProof {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.ec.serialize(writer)?;
+        self.lb.serialize(writer)?;
+        Ok(())
+    }
+
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self>
+    where
+        Self: std::marker::Sized,
+    {
+        let ec = ECProof::deserialize(reader)?;
+        let lb = Proof::deserialize(reader)?;
+        Ok(HybridProof { ec, lb })
+    }
+}
+
+// serde support: binary formats carry the `Serdes` bytes, human readable
+// formats carry them as a hex string; in both cases the encoding is decoded
+// strictly, and trailing bytes are rejected
+macro_rules! impl_serde {
+    ($($t:ty $(, $s:ident)?;)*) => {
+        $(
+            impl$(<$s: ParameterSet>)? ::serde::Serialize for $t {
+                fn serialize<Z: ::serde::Serializer>(
+                    &self,
+                    serializer: Z,
+                ) -> std::result::Result<Z::Ok, Z::Error> {
+                    to_serde(self, serializer)
+                }
+            }
+
+            impl<'de, $($s: ParameterSet)?> ::serde::Deserialize<'de> for $t {
+                fn deserialize<D: ::serde::Deserializer<'de>>(
+                    deserializer: D,
+                ) -> std::result::Result<Self, D::Error> {
+                    from_serde(deserializer)
+                }
+            }
+        )*
+    };
+}
+
+impl_serde! {
+    Poly256;
+    Poly32;
+    Param<S>, S;
+    PublicKey<S>, S;
+    SecretKey<S>, S;
+    Proof<S>, S;
+}
+
+fn to_serde<T: Serdes, Z: ::serde::Serializer>(
+    obj: &T,
+    serializer: Z,
+) -> std::result::Result<Z::Ok, Z::Error> {
+    // the buffers may hold a secret key
+    let mut buf = Zeroizing::new(vec![]);
+    Serdes::serialize(obj, &mut *buf).map_err(::serde::ser::Error::custom)?;
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&Zeroizing::new(hex::encode(&*buf)))
+    } else {
+        serializer.serialize_bytes(&buf)
+    }
+}
+
+fn from_serde<'de, T: Serdes, D: ::serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<T, D::Error> {
+    // a human readable format may also hold the bytes as an array
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(SerdesVisitor(PhantomData))
+    } else {
+        deserializer.deserialize_byte_buf(SerdesVisitor(PhantomData))
+    }
+}
+
+// decode an object from exactly the given bytes
+fn decode_exact<T: Serdes>(bytes: &[u8]) -> Result<T> {
+    let mut reader = bytes;
+    let res = T::deserialize(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(Error::MalformedEncoding("trailing bytes"));
+    }
+    Ok(res)
+}
+
+struct SerdesVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Serdes> ::serde::de::Visitor<'de> for SerdesVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a hex string or a byte array")
+    }
+
+    fn visit_str<E: ::serde::de::Error>(self, v: &str) -> std::result::Result<T, E> {
+        let bytes = Zeroizing::new(hex::decode(v).map_err(E::custom)?);
+        decode_exact(&bytes).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: ::serde::de::Error>(self, v: &[u8]) -> std::result::Result<T, E> {
+        decode_exact(v).map_err(E::custom)
+    }
+
+    // formats without a native byte string send a sequence of bytes
+    fn visit_seq<A: ::serde::de::SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> std::result::Result<T, A::Error> {
+        // the hint is untrusted; the largest object, a full param, fits in 64 KiB
+        let len = seq.size_hint().unwrap_or(0).min(1 << 16);
+        let mut bytes = Zeroizing::new(Vec::with_capacity(len));
+        while let Some(e) = seq.next_element::<u8>()? {
+            bytes.push(e);
+        }
+        decode_exact(&bytes).map_err(::serde::de::Error::custom)
+    }
+}
+
+fn read_height<R: Read>(reader: &mut R) -> Result<usize> {
+    let mut buf = [0u8; 1];
+    reader.read_exact(&mut buf)?;
+    let height = buf[0] as usize;
+    if height == 0 || height > MT_MAX_HEIGHT {
+        return Err(Error::MalformedEncoding("invalid tree height"));
+    }
+    Ok(height)
+}
+
+// all polynomials are written as a stream of coefficients of `bits` bits
+// each, most significant bit first, padded with zero bits to a whole byte.
+// a coefficient is lifted by `offset` to [0, bound), with bound <= 2^bits;
+// any other value is rejected by both the packer and the unpacker, so that
+// the encoding of a polynomial is unique. the check is accumulated and only
+// branched on at the end, as secret keys are (un)packed here as well
+fn pack_bits<W: Write>(
+    coeff: &[i32],
+    offset: i32,
+    bits: usize,
+    bound: u32,
+    writer: &mut W,
+) -> Result<()> {
+    let mut buf = Zeroizing::new(Vec::with_capacity((coeff.len() * bits).div_ceil(8)));
+    let mut invalid = 0u32;
+    let mut acc = 0u64;
+    let mut len = 0;
+    for e in coeff.iter() {
+        let v = e.wrapping_add(offset) as u32;
+        invalid |= (v >= bound) as u32;
+        acc = (acc << bits) | (v as u64 & ((1 << bits) - 1));
+        len += bits;
+        while len >= 8 {
+            len -= 8;
+            buf.push((acc >> len) as u8);
+        }
+    }
+    if len > 0 {
+        buf.push((acc << (8 - len)) as u8);
     }
+    acc.zeroize();
+    if invalid != 0 {
+        return Err(Error::MalformedEncoding("coefficient out of range"));
+    }
+    writer.write_all(&buf)?;
     Ok(())
 }
 
-fn pack_mod_p_poly<W: Write>(p: &Poly32, writer: &mut W) -> Result<()> {
-    for i in 0..8 {
-        let mut tmp: u128 = p.coeff[i * 4] as u128;
-        tmp <<= 22;
-        tmp += p.coeff[i * 4 + 1] as u128;
-        tmp <<= 22;
-        tmp += p.coeff[i * 4 + 2] as u128;
-        tmp <<= 22;
-        tmp += p.coeff[i * 4 + 3] as u128;
-        writer.write_all(&tmp.to_be_bytes()[5..16])?;
+fn unpack_bits<R: Read>(
+    res: &mut [i32],
+    offset: i32,
+    bits: usize,
+    bound: u32,
+    reader: &mut R,
+) -> Result<()> {
+    let mut buf = Zeroizing::new(vec![0u8; (res.len() * bits).div_ceil(8)]);
+    reader.read_exact(&mut buf)?;
+    let mask = (1u64 << bits) - 1;
+    let mut invalid = 0u32;
+    let mut acc = 0u64;
+    let mut len = 0;
+    let mut bytes = buf.iter();
+    for e in res.iter_mut() {
+        while len < bits {
+            acc = (acc << 8) | *bytes.next().unwrap() as u64;
+            len += 8;
+        }
+        len -= bits;
+        let v = ((acc >> len) & mask) as u32;
+        invalid |= (v >= bound) as u32;
+        *e = v as i32 - offset;
     }
+    // the padding bits
+    invalid |= (acc & ((1 << len) - 1)) as u32;
+    acc.zeroize();
+    if invalid != 0 {
+        return Err(Error::MalformedEncoding("coefficient out of range"));
+    }
+    Ok(())
+}
+
+// the coefficients of a trinary polynomial are lifted to [0, 2]
+fn pack_trinary<W: Write>(p: &Poly256, writer: &mut W) -> Result<()> {
+    pack_bits(&p.coeff, 1, 2, 3, writer)
+}
 
+fn unpack_trinary<R: Read>(res: &mut Poly256, reader: &mut R) -> Result<()> {
+    unpack_bits(&mut res.coeff, 1, 2, 3, reader)
+}
+
+// a challenge is a bitmap of the positions of its non-zero coefficients,
+// followed by one bit per non-zero coefficient in order of the positions,
+// set for 1; the unused bits of the last byte are zero
+fn pack_challenge<W: Write>(c: &Poly256, kappa: usize, writer: &mut W) -> Result<()> {
+    let mut buf = vec![0u8; 32 + kappa.div_ceil(8)];
+    let mut weight = 0;
+    for (i, e) in c.coeff.iter().enumerate() {
+        if *e == 0 {
+            continue;
+        }
+        if weight == kappa || e.abs() != 1 {
+            return Err(Error::MalformedEncoding("invalid challenge"));
+        }
+        buf[i >> 3] |= 0x80 >> (i & 7);
+        if *e == 1 {
+            buf[32 + (weight >> 3)] |= 0x80 >> (weight & 7);
+        }
+        weight += 1;
+    }
+    if weight != kappa {
+        return Err(Error::MalformedEncoding("invalid challenge"));
+    }
+    writer.write_all(&buf)?;
     Ok(())
 }
 
-fn unpack_mod_p_poly<R: Read>(res: &mut Poly32, reader: &mut R) -> Result<()> {
-    for i in 0..8 {
-        let mut buf = [0u8; 16];
-        reader.read_exact(&mut buf[5..16])?;
-
-        let mut tmp = u128::from_be_bytes(buf);
-        res.coeff[i * 4 + 3] = (tmp & 0x003F_FFFF) as i64;
-        tmp >>= 22;
-        res.coeff[i * 4 + 2] = (tmp & 0x003F_FFFF) as i64;
-        tmp >>= 22;
-        res.coeff[i * 4 + 1] = (tmp & 0x003F_FFFF) as i64;
-        tmp >>= 22;
-        res.coeff[i * 4] = tmp as i64;
+fn unpack_challenge<R: Read>(res: &mut Poly256, kappa: usize, reader: &mut R) -> Result<()> {
+    let mut buf = vec![0u8; 32 + kappa.div_ceil(8)];
+    reader.read_exact(&mut buf)?;
+    let weight: u32 = buf[0..32].iter().map(|e| e.count_ones()).sum();
+    if weight as usize != kappa {
+        return Err(Error::MalformedEncoding("challenge of wrong weight"));
+    }
+    let signs = &buf[32..];
+    if !kappa.is_multiple_of(8) && signs[kappa >> 3] & (0xff >> (kappa & 7)) != 0 {
+        return Err(Error::MalformedEncoding("non-zero padding in challenge"));
+    }
+    let mut weight = 0;
+    for (i, e) in res.coeff.iter_mut().enumerate() {
+        *e = 0;
+        if buf[i >> 3] & (0x80 >> (i & 7)) != 0 {
+            *e = if signs[weight >> 3] & (0x80 >> (weight & 7)) != 0 {
+                1
+            } else {
+                -1
+            };
+            weight += 1;
+        }
     }
     Ok(())
 }
 
+fn pack_mod_q_poly<W: Write>(p: &Poly256, writer: &mut W) -> Result<()> {
+    pack_bits(&p.coeff, 0, Q_BITS, Q as u32, writer)
+}
+
+fn unpack_mod_q_poly<R: Read>(res: &mut Poly256, reader: &mut R) -> Result<()> {
+    unpack_bits(&mut res.coeff, 0, Q_BITS, Q as u32, reader)
+}
+
+// the coefficients are lifted to [0, 2 * beta]
+fn pack_mod_beta_poly<W: Write>(p: &Poly256, beta: i32, bits: usize, writer: &mut W) -> Result<()> {
+    pack_bits(&p.coeff, beta, bits, 2 * beta as u32 + 1, writer)
+}
+
+fn unpack_mod_beta_poly<R: Read>(
+    res: &mut Poly256,
+    beta: i32,
+    bits: usize,
+    reader: &mut R,
+) -> Result<()> {
+    unpack_bits(&mut res.coeff, beta, bits, 2 * beta as u32 + 1, reader)
+}
+
+fn pack_mod_p_poly<W: Write>(p: &Poly32, writer: &mut W) -> Result<()> {
+    pack_bits(&p.coeff, 0, P_BITS, P as u32, writer)
+}
+
+fn unpack_mod_p_poly<R: Read>(res: &mut Poly32, reader: &mut R) -> Result<()> {
+    unpack_bits(&mut res.coeff, 0, P_BITS, P as u32, reader)
+}
+
 #[test]
 fn test_packing() {
+    use crate::param::{HighSecurity, BETA};
+    use crate::poly::{ShortSample, UniformSample};
     let mut rng = rand::thread_rng();
     let p = Poly256::uniform_random(&mut rng);
     let mut v: Vec<u8> = vec![];
@@ -294,9 +732,132 @@ fn test_packing() {
 
     let p = Poly256::rand_mod_beta(&mut rng);
     let mut v: Vec<u8> = vec![];
-    pack_mod_beta_poly(&p, &mut v).unwrap();
+    pack_mod_beta_poly(&p, BETA, 18, &mut v).unwrap();
+    assert_eq!(v.len(), 576);
+    let mut p2 = Poly256::zero();
+    unpack_mod_beta_poly(&mut p2, BETA, 18, &mut v[..].as_ref()).unwrap();
+    assert_eq!(p, p2);
+
+    // a width that is not a multiple of 8 bits per 4 coefficients
+    let (beta, bits) = (HighSecurity::BETA, HighSecurity::BETA_BITS);
+    assert_eq!(bits, 19);
+    let p = crate::poly256::rand_mod_bound(
+        &mut rng,
+        beta,
+        HighSecurity::BETA_M2_P1,
+        HighSecurity::BETA_RS_RANGE,
+    );
+    let mut v: Vec<u8> = vec![];
+    pack_mod_beta_poly(&p, beta, bits, &mut v).unwrap();
+    assert_eq!(v.len(), 608);
     let mut p2 = Poly256::zero();
-    unpack_mod_beta_poly(&mut p2, &mut v[..].as_ref()).unwrap();
+    unpack_mod_beta_poly(&mut p2, beta, bits, &mut v[..].as_ref()).unwrap();
     assert_eq!(p, p2);
     // assert!(false);
 }
+
+#[test]
+fn test_challenge_packing() {
+    use crate::lbvrf::hash_to_challenge;
+    use crate::param::{HighSecurity, Standard};
+    let c = hash_to_challenge::<Standard>(b"input").unwrap();
+    let mut v = vec![];
+    pack_challenge(&c, 39, &mut v).unwrap();
+    assert_eq!(v.len(), Standard::CHALLENGE_LEN);
+    assert_eq!(v.len(), 37);
+    let mut c2 = Poly256::zero();
+    unpack_challenge(&mut c2, 39, &mut v[..].as_ref()).unwrap();
+    assert_eq!(c, c2);
+
+    // a position more or less, and a padding bit
+    let mut w = v.clone();
+    w[31] ^= 1;
+    assert!(unpack_challenge(&mut c2, 39, &mut w[..].as_ref()).is_err());
+    let mut w = v.clone();
+    w[36] |= 1;
+    assert!(unpack_challenge(&mut c2, 39, &mut w[..].as_ref()).is_err());
+    assert!(pack_challenge(&c, 38, &mut vec![]).is_err());
+    assert!(pack_challenge(&c, 40, &mut vec![]).is_err());
+    let mut c3 = c;
+    c3.coeff[c.coeff.iter().position(|e| *e != 0).unwrap()] = 2;
+    assert!(pack_challenge(&c3, 39, &mut vec![]).is_err());
+
+    // KAPPA is a multiple of 8
+    let c = hash_to_challenge::<HighSecurity>(b"input").unwrap();
+    let mut v = vec![];
+    pack_challenge(&c, 60, &mut v).unwrap();
+    assert_eq!(v.len(), HighSecurity::CHALLENGE_LEN);
+    unpack_challenge(&mut c2, 60, &mut v[..].as_ref()).unwrap();
+    assert_eq!(c, c2);
+}
+
+#[test]
+fn test_strict_unpacking() {
+    use crate::param::{BETA, P_POLY_LEN, Q_POLY_LEN};
+    let mut p = Poly256::zero();
+
+    // the first coefficient is written in the leading 27 bits
+    let mut q = Poly256::zero();
+    q.coeff[0] = Q - 1;
+    let mut v = vec![];
+    pack_mod_q_poly(&q, &mut v).unwrap();
+    assert_eq!(v.len(), Q_POLY_LEN);
+    unpack_mod_q_poly(&mut p, &mut v[..].as_ref()).unwrap();
+    assert_eq!(p, q);
+    v[0..4].copy_from_slice(&((Q as u32) << 5).to_be_bytes());
+    assert!(unpack_mod_q_poly(&mut p, &mut v[..].as_ref()).is_err());
+    q.coeff[0] = Q;
+    assert!(pack_mod_q_poly(&q, &mut vec![]).is_err());
+    q.coeff[0] = -1;
+    assert!(pack_mod_q_poly(&q, &mut vec![]).is_err());
+
+    // the last coefficient mod P is written in the trailing 22 bits
+    let mut a = Poly32::zero();
+    a.coeff[31] = P - 1;
+    let mut v = vec![];
+    pack_mod_p_poly(&a, &mut v).unwrap();
+    assert_eq!(v.len(), P_POLY_LEN);
+    let mut b = Poly32::zero();
+    unpack_mod_p_poly(&mut b, &mut v[..].as_ref()).unwrap();
+    assert_eq!(a, b);
+    v[84..88].copy_from_slice(&(P as u32).to_be_bytes());
+    assert!(unpack_mod_p_poly(&mut b, &mut v[..].as_ref()).is_err());
+    a.coeff[31] = P;
+    assert!(pack_mod_p_poly(&a, &mut vec![]).is_err());
+
+    // the pattern 0b11, at each position of a byte
+    let v = [0b0101_0101u8; 64];
+    unpack_trinary(&mut p, &mut v[..].as_ref()).unwrap();
+    assert!(p.coeff.iter().all(|e| *e == 0));
+    for i in 0..4 {
+        let mut w = v;
+        w[63] |= 0b11 << (2 * i);
+        assert!(unpack_trinary(&mut p, &mut w[..].as_ref()).is_err());
+    }
+    q = Poly256::zero();
+    q.coeff[3] = 2;
+    assert!(pack_trinary(&q, &mut vec![]).is_err());
+
+    // coefficients outside of [-BETA, BETA]
+    let mut q = Poly256::zero();
+    q.coeff[0] = BETA;
+    q.coeff[255] = -BETA;
+    let mut v = vec![];
+    pack_mod_beta_poly(&q, BETA, 18, &mut v).unwrap();
+    unpack_mod_beta_poly(&mut p, BETA, 18, &mut v[..].as_ref()).unwrap();
+    assert_eq!(p, q);
+    q.coeff[0] = BETA + 1;
+    assert!(pack_mod_beta_poly(&q, BETA, 18, &mut vec![]).is_err());
+    let v = vec![0xffu8; 576];
+    assert!(unpack_mod_beta_poly(&mut p, BETA, 18, &mut v[..].as_ref()).is_err());
+
+    // padding bits, for a length that is not a whole number of bytes
+    let mut r = [0i32; 3];
+    unpack_bits(&mut r, 0, 5, 32, &mut [0u8, 0][..].as_ref()).unwrap();
+    assert!(unpack_bits(&mut r, 0, 5, 32, &mut [0u8, 1][..].as_ref()).is_err());
+    let mut v = vec![];
+    pack_bits(&[31, 0, 31], 0, 5, 32, &mut v).unwrap();
+    assert_eq!(v, vec![0b1111_1000, 0b0011_1110]);
+    unpack_bits(&mut r, 0, 5, 32, &mut v[..].as_ref()).unwrap();
+    assert_eq!(r, [31, 0, 31]);
+}
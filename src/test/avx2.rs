@@ -0,0 +1,129 @@
+// differential tests between the AVX2 kernels and the portable code
+use crate::avx2::assert_scalar_eq;
+use crate::lbvrf::{check_norm, LBVRF};
+use crate::param::{Param, BETA_M_KAPPA, Q};
+use crate::poly::{PolyArith, ShortSample, UniformSample};
+use crate::poly256::*;
+use crate::serde::Serdes;
+use crate::VRF;
+use rand::{Rng, RngCore};
+
+fn has_avx2() -> bool {
+    if !is_x86_feature_detected!("avx2") {
+        println!("avx2 is not supported; skipped");
+        return false;
+    }
+    true
+}
+
+// polynomials with canonical, centered, and arbitrary coefficients
+fn test_polys() -> Vec<Poly256> {
+    let mut rng = rand::thread_rng();
+    let mut res = vec![Poly256::zero()];
+    for _ in 0..10 {
+        let a = Poly256::uniform_random(&mut rng);
+        let mut b = a;
+        b.centered();
+        let mut c = Poly256::zero();
+        for e in c.coeff.iter_mut() {
+            *e = rng.next_u32() as i32;
+        }
+        res.push(a);
+        res.push(b);
+        res.push(c);
+        res.push(Poly256::rand_trinary(&mut rng));
+        res.push(Poly256::rand_mod_beta(&mut rng));
+    }
+    let mut edge = Poly256::zero();
+    for (i, e) in edge.coeff.iter_mut().enumerate() {
+        *e = [0, 1, -1, Q - 1, 1 - Q, Q, -Q, i32::MAX, i32::MIN][i % 9];
+    }
+    res.push(edge);
+    res
+}
+
+#[test]
+fn test_avx2_ntt() {
+    if !has_avx2() {
+        return;
+    }
+    for a in test_polys().iter() {
+        let a_ntt = assert_scalar_eq(|| a.ntt());
+        assert_scalar_eq(|| a.inverse_ntt());
+        assert_scalar_eq(|| a_ntt.inverse_ntt());
+    }
+}
+
+#[test]
+fn test_avx2_arith() {
+    if !has_avx2() {
+        return;
+    }
+    let polys = test_polys();
+    for (a, b) in polys.iter().zip(polys.iter().rev()) {
+        assert_scalar_eq(|| Poly256::add(a, b));
+        assert_scalar_eq(|| Poly256::sub(a, b));
+        assert_scalar_eq(|| Poly256::mul(a, b));
+        assert_scalar_eq(|| pointwise_mul(a, b));
+    }
+    // the portable inner product expects reduced inputs
+    let polys: Vec<Poly256> = polys
+        .into_iter()
+        .filter(|x| x.coeff.iter().all(|e| e.unsigned_abs() < Q as u32))
+        .collect();
+    for (a, b) in polys.chunks(9).zip(polys.rchunks(9)) {
+        assert_scalar_eq(|| poly256_inner_product_ntt_domain(a, b));
+        assert_scalar_eq(|| poly256_inner_product(a, b));
+    }
+}
+
+#[test]
+fn test_avx2_check_norm() {
+    if !has_avx2() {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    let values = [
+        0,
+        BETA_M_KAPPA,
+        -BETA_M_KAPPA,
+        BETA_M_KAPPA + 1,
+        -BETA_M_KAPPA - 1,
+        i32::MAX,
+        i32::MIN,
+    ];
+    for v in values.iter() {
+        let mut z = [Poly256::zero(); 9];
+        z[rng.gen_range(0, 9)].coeff[rng.gen_range(0, 256)] = *v;
+        assert_scalar_eq(|| check_norm(&z, BETA_M_KAPPA));
+        assert_eq!(
+            check_norm(&z, BETA_M_KAPPA),
+            v.unsigned_abs() <= BETA_M_KAPPA as u32
+        );
+    }
+}
+
+#[test]
+fn test_avx2_proofs() {
+    if !has_avx2() {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    for _ in 0..5 {
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        let message = "this is a message that vrf signs";
+        let run = || {
+            let param: Param = <LBVRF as VRF>::paramgen(seed).unwrap();
+            let (pk, sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
+            let proof = <LBVRF as VRF>::prove(message, &param, &pk, &sk, seed).unwrap();
+            let output = <LBVRF as VRF>::verify(message, &param, &pk, &proof).unwrap();
+            let mut buf: Vec<u8> = vec![];
+            pk.serialize(&mut buf).unwrap();
+            proof.serialize(&mut buf).unwrap();
+            (buf, output)
+        };
+        let (_, output) = assert_scalar_eq(run);
+        assert!(output.is_some());
+    }
+}
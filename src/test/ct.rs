@@ -0,0 +1,236 @@
+// a dudect style statistical timing test, following
+// "dude, is my code constant time?" by Reparaz, Balasch and Verbauwhede
+//
+// each function is timed on two classes of inputs, a fixed one and a random
+// one, measured in a random order. a Welch t-test is then run on the two
+// timing distributions, once on the raw measurements and once for each of
+// a few cropping percentiles. a |t| above T_THRESHOLD means the timing very
+// likely depends on the input.
+//
+// the tests are slow and only meaningful in release mode, so they are
+// ignored by default; run them locally with
+//     cargo test --release -- --ignored timing
+
+use crate::lbvrf::check_norm;
+use crate::param::{BETA, BETA_M_KAPPA, Q};
+use crate::poly::{PolyArith, ShortSample, TrinaryMul};
+use crate::poly256::Poly256;
+use rand::{Rng, RngCore};
+use std::hint::black_box;
+
+/// dudect reports a leak with high confidence above this value
+const T_THRESHOLD: f64 = 10.0;
+
+/// number of cropped tests besides the raw one
+const NUM_PERCENTILES: usize = 20;
+
+/// online mean and variance of the two classes, using Welford's method
+#[derive(Default)]
+struct TTest {
+    n: [f64; 2],
+    mean: [f64; 2],
+    m2: [f64; 2],
+}
+
+impl TTest {
+    fn push(&mut self, class: usize, x: f64) {
+        self.n[class] += 1.0;
+        let delta = x - self.mean[class];
+        self.mean[class] += delta / self.n[class];
+        self.m2[class] += delta * (x - self.mean[class]);
+    }
+
+    fn t(&self) -> f64 {
+        if self.n[0] < 2.0 || self.n[1] < 2.0 {
+            return 0.0;
+        }
+        let var0 = self.m2[0] / (self.n[0] - 1.0);
+        let var1 = self.m2[1] / (self.n[1] - 1.0);
+        let den = (var0 / self.n[0] + var1 / self.n[1]).sqrt();
+        if den == 0.0 {
+            return 0.0;
+        }
+        (self.mean[0] - self.mean[1]) / den
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn cycles() -> u64 {
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn cycles() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64
+}
+
+/// time `f` over `n` inputs from `gen`, where `gen(rng, 0)` returns the
+/// fixed class and `gen(rng, 1)` the random class; returns the largest |t|
+fn dudect<I, G, F>(n: usize, mut gen: G, f: F) -> f64
+where
+    G: FnMut(&mut rand::rngs::ThreadRng, usize) -> I,
+    F: Fn(&I),
+{
+    let mut rng = rand::thread_rng();
+    // all inputs are prepared before measuring
+    let inputs: Vec<(usize, I)> = (0..n)
+        .map(|_| {
+            let class = (rng.next_u32() & 1) as usize;
+            (class, gen(&mut rng, class))
+        })
+        .collect();
+
+    // warm up the caches and branch predictors
+    for (_, input) in inputs.iter().take(n / 10) {
+        f(black_box(input));
+    }
+
+    let times: Vec<u64> = inputs
+        .iter()
+        .map(|(_, input)| {
+            let start = cycles();
+            f(black_box(input));
+            cycles().wrapping_sub(start)
+        })
+        .collect();
+
+    let mut sorted = times.clone();
+    sorted.sort_unstable();
+    let mut thresholds = vec![u64::MAX];
+    for i in 0..NUM_PERCENTILES {
+        let p = 1.0 - 0.5f64.powf(10.0 * (i + 1) as f64 / NUM_PERCENTILES as f64);
+        thresholds.push(sorted[((n as f64 * p) as usize).min(n - 1)]);
+    }
+
+    let mut max_t = 0f64;
+    for threshold in thresholds.iter() {
+        let mut test = TTest::default();
+        for ((class, _), time) in inputs.iter().zip(times.iter()) {
+            if time < threshold {
+                test.push(*class, *time as f64);
+            }
+        }
+        max_t = max_t.max(test.t().abs());
+    }
+    max_t
+}
+
+fn rand_mod_q<R: RngCore>(rng: &mut R) -> Poly256 {
+    let mut res = Poly256::zero();
+    for e in res.coeff.iter_mut() {
+        *e = rng.gen_range(0, Q);
+    }
+    res
+}
+
+fn rand_z<R: RngCore>(rng: &mut R) -> [Poly256; 9] {
+    let mut z = [Poly256::zero(); 9];
+    for e in z.iter_mut() {
+        for f in e.coeff.iter_mut() {
+            *f = rng.gen_range(-BETA, BETA + 1);
+        }
+    }
+    z
+}
+
+// the norm check of the original implementation, which returns as soon
+// as it finds a large coefficient
+fn leaky_check_norm(z: &[Poly256; 9]) -> bool {
+    for e in z.iter() {
+        for f in e.coeff.iter() {
+            if *f > BETA_M_KAPPA || *f < -BETA_M_KAPPA {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[test]
+#[ignore]
+fn test_timing_harness_detects_leak() {
+    // the harness must flag an early return
+    let t = dudect(
+        100_000,
+        |rng, class| {
+            let mut z = rand_z(rng);
+            if class == 0 {
+                z[0].coeff[0] = BETA;
+            }
+            z
+        },
+        |z| {
+            black_box(leaky_check_norm(z));
+        },
+    );
+    println!("leaky check_norm: t = {:.2}", t);
+    assert!(t > T_THRESHOLD);
+}
+
+#[test]
+#[ignore]
+fn test_timing_check_norm() {
+    let t = dudect(
+        100_000,
+        |rng, class| {
+            let mut z = rand_z(rng);
+            if class == 0 {
+                z[0].coeff[0] = BETA;
+            }
+            z
+        },
+        |z| {
+            black_box(check_norm(z, BETA_M_KAPPA));
+        },
+    );
+    println!("check_norm: t = {:.2}", t);
+    assert!(t < T_THRESHOLD);
+}
+
+#[test]
+#[ignore]
+fn test_timing_mul_trinary() {
+    // both the trinary polynomial (keygen) and the other operand (prove)
+    // are secret
+    let t = dudect(
+        20_000,
+        |rng, class| {
+            if class == 0 {
+                (Poly256::zero(), Poly256::zero())
+            } else {
+                (rand_mod_q(rng), Poly256::rand_trinary(rng))
+            }
+        },
+        |(a, b)| {
+            black_box(Poly256::mul_trinary(a, b));
+        },
+    );
+    println!("mul_trinary: t = {:.2}", t);
+    assert!(t < T_THRESHOLD);
+}
+
+#[test]
+#[ignore]
+fn test_timing_centered() {
+    let t = dudect(
+        100_000,
+        |rng, class| {
+            if class == 0 {
+                Poly256::zero()
+            } else {
+                rand_mod_q(rng)
+            }
+        },
+        |a| {
+            let mut a = *a;
+            a.centered();
+            black_box(a);
+        },
+    );
+    println!("centered: t = {:.2}", t);
+    assert!(t < T_THRESHOLD);
+}
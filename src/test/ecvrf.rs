@@ -0,0 +1,165 @@
+use crate::ecvrf::*;
+use crate::keypair::ECPublicKey;
+use crate::serde::Serdes;
+use crate::VRF;
+
+// (SK, PK, alpha, pi, beta) from RFC 9381, appendix B.3
+const TAI_VECTORS: [(&str, &str, &str, &str, &str); 3] = [
+    (
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+        "",
+        "8657106690b5526245a92b003bb079ccd1a92130477671f6fc01ad16f26f723f26f8a57ccaed74ee1b190bed1f479d9727d2d0f9b005a6e456a35d4fb0daab1268a1b0db10836d9826a528ca76567805",
+        "90cf1df3b703cce59e2a35b925d411164068269d7b2d29f3301c03dd757876ff66b71dda49d2de59d03450451af026798e8f81cd2e333de5cdf4f3e140fdd8ae",
+    ),
+    (
+        "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+        "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+        "72",
+        "f3141cd382dc42909d19ec5110469e4feae18300e94f304590abdced48aed5933bf0864a62558b3ed7f2fea45c92a465301b3bbf5e3e54ddf2d935be3b67926da3ef39226bbc355bdc9850112c8f4b02",
+        "eb4440665d3891d668e7e0fcaf587f1b4bd7fbfe99d0eb2211ccec90496310eb5e33821bc613efb94db5e5b54c70a848a0bef4553a41befc57663b56373a5031",
+    ),
+    (
+        "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+        "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+        "af82",
+        "9bc0f79119cc5604bf02d23b4caede71393cedfbb191434dd016d30177ccbf8096bb474e53895c362d8628ee9f9ea3c0e52c7a5c691b6c18c9979866568add7a2d41b00b05081ed0f58ee5e31b3a970e",
+        "645427e5d00c62a23fb703732fa5d892940935942101e456ecca7bb217c61c452118fec1219202a0edcf038bb6373241578be7217ba85a2687f7a0310b2df19f",
+    ),
+];
+
+// (SK, PK, alpha, pi, beta) from RFC 9381, appendix B.4
+const ELL2_VECTORS: [(&str, &str, &str, &str, &str); 3] = [
+    (
+        "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+        "",
+        "7d9c633ffeee27349264cf5c667579fc583b4bda63ab71d001f89c10003ab46f14adf9a3cd8b8412d9038531e865c341cafa73589b023d14311c331a9ad15ff2fb37831e00f0acaa6d73bc9997b06501",
+        "9d574bf9b8302ec0fc1e21c3ec5368269527b87b462ce36dab2d14ccf80c53cccf6758f058c5b1c856b116388152bbe509ee3b9ecfe63d93c3b4346c1fbc6c54",
+    ),
+    (
+        "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+        "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+        "72",
+        "47b327393ff2dd81336f8a2ef10339112401253b3c714eeda879f12c509072ef055b48372bb82efbdce8e10c8cb9a2f9d60e93908f93df1623ad78a86a028d6bc064dbfc75a6a57379ef855dc6733801",
+        "38561d6b77b71d30eb97a062168ae12b667ce5c28caccdf76bc88e093e4635987cd96814ce55b4689b3dd2947f80e59aac7b7675f8083865b46c89b2ce9cc735",
+    ),
+    (
+        "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+        "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+        "af82",
+        "926e895d308f5e328e7aa159c06eddbe56d06846abf5d98c2512235eaa57fdce35b46edfc655bc828d44ad09d1150f31374e7ef73027e14760d42e77341fe05467bb286cc2c9d7fde29120a0b2320d04",
+        "121b7f9b9aaaa29099fc04a94ba52784d44eac976dd1a3cca458733be5cd090a7b5fbd148444f17f8daf1fb55cb04b1ae85a626e30a54b4b0f8abf4a43314a58",
+    ),
+];
+
+// (msg, x, y) from RFC 9380, appendix J.5.2, in big endian
+const H2C_VECTORS: [(&str, &str, &str); 3] = [
+    (
+        "",
+        "1ff2b70ecf862799e11b7ae744e3489aa058ce805dd323a936375a84695e76da",
+        "222e314d04a4d5725e9f2aff9fb2a6b69ef375a1214eb19021ceab2d687f0f9b",
+    ),
+    (
+        "abc",
+        "5f13cc69c891d86927eb37bd4afc6672360007c63f68a33ab423a3aa040fd2a8",
+        "67732d50f9a26f73111dd1ed5dba225614e538599db58ba30aaea1f5c827fa42",
+    ),
+    (
+        "abcdef0123456789",
+        "1dd2fefce934ecfd7aae6ec998de088d7dd03316aa1847198aecf699ba6613f1",
+        "2f8a6c24dd1adde73909cada6a4a137577b0f179d336685c4a955a0a8e1a86fb",
+    ),
+];
+
+fn to_array<const N: usize>(s: &str) -> [u8; N] {
+    let mut res = [0u8; N];
+    res.copy_from_slice(&hex::decode(s).unwrap());
+    res
+}
+
+fn check_vectors(suite: ECVRFSuite, vectors: &[(&str, &str, &str, &str, &str)]) {
+    for (sk, pk, alpha, pi, beta) in vectors.iter() {
+        let (pk_, sk_) = <ECVRF as VRF>::keygen(to_array(sk), &suite).unwrap();
+        let mut buf: Vec<u8> = vec![];
+        assert!(pk_.serialize(&mut buf).is_ok());
+        assert_eq!(hex::encode(&buf), *pk);
+
+        let alpha = hex::decode(alpha).unwrap();
+        let proof = <ECVRF as VRF>::prove(&alpha, &suite, &pk_, &sk_, [0u8; 32]).unwrap();
+        assert_eq!(hex::encode(&proof.to_bytes()[..]), *pi);
+
+        let res = <ECVRF as VRF>::verify(&alpha, &suite, &pk_, &proof).unwrap();
+        assert_eq!(hex::encode(&res.unwrap()[..]), *beta);
+        assert_eq!(proof.proof_to_hash(suite), res.unwrap());
+
+        let pi = hex::decode(pi).unwrap();
+        let proof2 = ECProof::deserialize(&mut pi.as_slice()).unwrap();
+        assert_eq!(proof, proof2);
+
+        // wrong message
+        let res = <ECVRF as VRF>::verify("another message", &suite, &pk_, &proof).unwrap();
+        assert!(res.is_none());
+    }
+}
+
+#[test]
+fn test_ecvrf_tai_vectors() {
+    check_vectors(ECVRFSuite::Tai, &TAI_VECTORS);
+}
+
+#[test]
+fn test_ecvrf_ell2_vectors() {
+    check_vectors(ECVRFSuite::Ell2, &ELL2_VECTORS);
+}
+
+#[test]
+fn test_h2c_encode_to_curve() {
+    let dst = b"QUUX-V01-CS02-with-edwards25519_XMD:SHA-512_ELL2_NU_";
+    for (msg, x, y) in H2C_VECTORS.iter() {
+        let point = h2c_encode_to_curve(msg.as_bytes(), dst);
+        let mut y: [u8; 32] = to_array(y);
+        let x: [u8; 32] = to_array(x);
+        y.reverse();
+        y[31] |= (x[31] & 1) << 7;
+        assert_eq!(point.compress().to_bytes(), y);
+    }
+}
+
+#[test]
+fn test_ecvrf_errors() {
+    let suite = <ECVRF as VRF>::paramgen([0u8; 32]).unwrap();
+    let (pk, sk) = <ECVRF as VRF>::keygen([1u8; 32], &suite).unwrap();
+    let (pk2, _sk2) = <ECVRF as VRF>::keygen([2u8; 32], &suite).unwrap();
+    let message = "this is a message that vrf signs";
+
+    // mismatched key pair
+    assert!(<ECVRF as VRF>::prove(message, &suite, &pk2, &sk, [0u8; 32]).is_err());
+
+    // proofs do not verify under another key or suite
+    let proof = <ECVRF as VRF>::prove(message, &suite, &pk, &sk, [0u8; 32]).unwrap();
+    assert!(<ECVRF as VRF>::verify(message, &suite, &pk2, &proof)
+        .unwrap()
+        .is_none());
+    assert!(
+        <ECVRF as VRF>::verify(message, &ECVRFSuite::Ell2, &pk, &proof)
+            .unwrap()
+            .is_none()
+    );
+
+    // small order public key: the identity point
+    let mut identity = [0u8; 32];
+    identity[0] = 1;
+    let small = ECPublicKey::deserialize(&mut identity.as_ref()).unwrap();
+    assert!(<ECVRF as VRF>::verify(message, &suite, &small, &proof)
+        .unwrap()
+        .is_none());
+
+    // non-canonical s
+    let mut bytes = proof.to_bytes();
+    bytes[79] = 0xff;
+    assert!(ECProof::from_bytes(&bytes).is_err());
+
+    // unknown suite
+    assert!(ECVRFSuite::deserialize(&mut [0u8].as_ref()).is_err());
+}
@@ -0,0 +1,144 @@
+use crate::envelope::*;
+use crate::error::Error;
+use crate::keypair::{PublicKey, SecretKey};
+use crate::lbvrf::{Proof, LBVRF};
+use crate::param::{HighSecurity, Param, ParameterSet, Standard};
+use crate::serde::Serdes;
+use crate::VRF;
+use sha2::Digest;
+
+fn bytes<T: Serdes>(obj: &T) -> Vec<u8> {
+    let mut buf = vec![];
+    obj.serialize(&mut buf).unwrap();
+    buf
+}
+
+fn check_set<S: ParameterSet>() {
+    let pp = Param::<S>::from_seed([0u8; 32]);
+    let (pk, sk) = LBVRF::<S>::keygen([1u8; 32], &pp).unwrap();
+    let proof = LBVRF::<S>::prove("message", &pp, &pk, &sk, [2u8; 32]).unwrap();
+
+    // the header, the `Serdes` bytes and the checksum
+    let sealed = seal(&pk, &pp.digest).unwrap();
+    assert_eq!(sealed.len(), HEADER_LEN + S::PK_LEN + CHECKSUM_LEN);
+    assert_eq!(&sealed[HEADER_LEN..HEADER_LEN + S::PK_LEN], &bytes(&pk)[..]);
+    assert_eq!(
+        Header::parse(&sealed).unwrap(),
+        Header {
+            version: VERSION,
+            object_type: ObjectType::PublicKey,
+            set_id: S::ID,
+            digest: pp.digest,
+        }
+    );
+
+    assert_eq!(
+        open_param::<S>(&seal(&pp, &pp.digest).unwrap()).unwrap(),
+        pp
+    );
+    assert_eq!(open::<PublicKey<S>>(&sealed, &pp.digest).unwrap(), pk);
+    let sealed = seal(&sk, &pp.digest).unwrap();
+    assert_eq!(open::<SecretKey<S>>(&sealed, &pp.digest).unwrap(), sk);
+    let sealed = seal(&proof, &pp.digest).unwrap();
+    assert_eq!(open::<Proof<S>>(&sealed, &pp.digest).unwrap(), proof);
+
+    let pem = to_pem(&pk, &pp.digest).unwrap();
+    assert!(pem.starts_with("-----BEGIN LB-VRF PUBLIC KEY-----\n"));
+    assert!(pem.ends_with("-----END LB-VRF PUBLIC KEY-----\n"));
+    assert!(pem.lines().all(|e| e.len() <= 64));
+    assert_eq!(from_pem::<PublicKey<S>>(&pem, &pp.digest).unwrap(), pk);
+    let pem = to_pem(&proof, &pp.digest).unwrap();
+    assert!(pem.starts_with("-----BEGIN LB-VRF PROOF-----\n"));
+    assert_eq!(from_pem::<Proof<S>>(&pem, &pp.digest).unwrap(), proof);
+}
+
+#[test]
+fn test_envelope() {
+    check_set::<Standard>();
+    check_set::<HighSecurity>();
+}
+
+#[test]
+fn test_envelope_mismatch() {
+    let pp = Param::<Standard>::from_seed([0u8; 32]);
+    let other = Param::<Standard>::from_seed([1u8; 32]);
+    let (pk, _sk) = LBVRF::keygen([1u8; 32], &pp).unwrap();
+    let sealed = seal(&pk, &pp.digest).unwrap();
+
+    // another param
+    assert!(matches!(
+        open::<PublicKey>(&sealed, &other.digest),
+        Err(Error::ParamMismatch)
+    ));
+    assert!(matches!(
+        seal(&pp, &other.digest),
+        Err(Error::ParamMismatch)
+    ));
+    assert!(matches!(
+        seal(&pk, &other.digest),
+        Err(Error::ParamMismatch)
+    ));
+    // another parameter set, another object type
+    assert!(matches!(
+        open::<PublicKey<HighSecurity>>(&sealed, &pp.digest),
+        Err(Error::ParamMismatch)
+    ));
+    assert!(matches!(
+        open::<Proof>(&sealed, &pp.digest),
+        Err(Error::MalformedEncoding(_))
+    ));
+    let high = Param::<HighSecurity>::from_seed([0u8; 32]);
+    assert!(matches!(
+        open_param::<Standard>(&seal(&high, &high.digest).unwrap()),
+        Err(Error::ParamMismatch)
+    ));
+
+    // a param whose header does not match its own digest
+    let mut forged = seal(&other, &other.digest).unwrap();
+    forged[7..HEADER_LEN].copy_from_slice(&pp.digest);
+    let len = forged.len() - CHECKSUM_LEN;
+    let checksum = sha2::Sha512::digest(&forged[..len]);
+    forged[len..].copy_from_slice(&checksum[0..CHECKSUM_LEN]);
+    assert!(matches!(
+        open_param::<Standard>(&forged),
+        Err(Error::ParamMismatch)
+    ));
+}
+
+#[test]
+fn test_envelope_corruption() {
+    let pp = Param::<Standard>::from_seed([0u8; 32]);
+    let (pk, _sk) = LBVRF::keygen([1u8; 32], &pp).unwrap();
+    let sealed = seal(&pk, &pp.digest).unwrap();
+
+    // any flipped bit is caught, either by the magic bytes, the version
+    // or the checksum
+    for i in (0..sealed.len()).step_by(97).chain(0..HEADER_LEN) {
+        let mut corrupted = sealed.to_vec();
+        corrupted[i] ^= 1;
+        assert!(open::<PublicKey>(&corrupted, &pp.digest).is_err());
+    }
+    let mut corrupted = sealed.to_vec();
+    corrupted[4] = VERSION + 1;
+    assert!(matches!(
+        Header::parse(&corrupted),
+        Err(Error::UnsupportedVersion(2))
+    ));
+    assert!(Header::parse(&sealed[..HEADER_LEN]).is_err());
+    assert!(open::<PublicKey>(&sealed[..sealed.len() - 1], &pp.digest).is_err());
+    assert!(open::<PublicKey>(&bytes(&pk), &pp.digest).is_err());
+
+    // the armor must match the envelope
+    let pem = to_pem(&pk, &pp.digest).unwrap();
+    assert_eq!(&*dearmor(&pem).unwrap(), &*sealed);
+    assert_eq!(&*dearmor(&format!("\n  {}  \n", &*pem)).unwrap(), &*sealed);
+    let relabeled = pem.replace("PUBLIC KEY", "PROOF");
+    assert!(dearmor(&relabeled).is_err());
+    let truncated = pem.replace("-----END LB-VRF PUBLIC KEY-----\n", "");
+    assert!(dearmor(&truncated).is_err());
+    let trailing = format!("{}trailing\n", &*pem);
+    assert!(dearmor(&trailing).is_err());
+    let invalid = pem.replacen('A', "!", 1);
+    assert!(dearmor(&invalid).is_err());
+    assert!(dearmor(&bytes(&pk).iter().map(|e| *e as char).collect::<String>()).is_err());
+}
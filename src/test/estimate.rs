@@ -0,0 +1,44 @@
+use crate::estimate::*;
+use crate::param::*;
+
+#[test]
+fn test_estimate_constants() {
+    let standard = Setting::from_set::<Standard>().estimate();
+    assert_eq!(standard.q_rs_range, Q_RS_RANGE);
+    assert_eq!(standard.p_rs_range, P_RS_RANGE);
+    assert_eq!(standard.r_base, R_BASE.to_vec());
+    assert_eq!(standard.beta_rs_range, BETA_RS_RANGE);
+    assert_eq!(standard.beta_rs_range, Standard::BETA_RS_RANGE);
+    assert_eq!(rs_range(BETA_M2_P1), BETA_RS_RANGE);
+
+    let high = Setting::from_set::<HighSecurity>().estimate();
+    assert_eq!(high.q_rs_range, Q_RS_RANGE);
+    assert_eq!(high.beta_rs_range, HighSecurity::BETA_RS_RANGE);
+
+    // x^32+R divides x^256+1, i.e., (-R)^8 = -1 mod P
+    let base = r_base(P as u32, R as u32, 9);
+    assert_eq!(&base[0..8], &R_BASE[..]);
+    assert_eq!(base[8], P - 1);
+}
+
+#[test]
+fn test_estimate_security() {
+    let standard = Setting::from_set::<Standard>().estimate();
+    let high = Setting::from_set::<HighSecurity>().estimate();
+
+    // BETA = KAPPA * 256 * L, so both sets repeat about e times
+    for e in [&standard, &high].iter() {
+        assert!((e.repetitions - std::f64::consts::E).abs() < 0.01);
+    }
+    // at 2^-128 the tail of c * s is the worst case KAPPA, hence BETA_M_KAPPA
+    assert_eq!(standard.cs_tail, KAPPA);
+    assert_eq!(high.cs_tail, HighSecurity::KAPPA);
+    let tail = cs_tail(200, 256);
+    assert!(tail > 39 && tail < 200);
+
+    // about 128 bits, bounded by key recovery
+    assert!((standard.classical_bits() - 128.0).abs() < 1.0);
+    assert!(standard.mlwe_block_size < standard.msis_block_size);
+    assert!(standard.quantum_bits() < standard.classical_bits());
+    assert!(high.classical_bits() > standard.classical_bits());
+}
@@ -0,0 +1,73 @@
+use crate::hybrid::*;
+use crate::keypair::{HybridPublicKey, HybridSecretKey};
+use crate::serde::Serdes;
+use crate::VRF;
+
+#[test]
+fn test_hybrid_vrf() {
+    let seed = [0u8; 32];
+    let param = <HybridVRF as VRF>::paramgen(seed).unwrap();
+    let (pk, sk) = <HybridVRF as VRF>::keygen(seed, &param).unwrap();
+    let message = "this is a message that vrf signs";
+    let proof = <HybridVRF as VRF>::prove(message, &param, &pk, &sk, seed).unwrap();
+
+    let res = <HybridVRF as VRF>::verify(message, &param, &pk, &proof).unwrap();
+    assert_eq!(res, Some(proof.proof_to_hash(&param).unwrap()));
+    assert_eq!(
+        res.unwrap(),
+        combine_outputs(
+            &proof.ec_proof().proof_to_hash(param.ec),
+            &proof.lb_proof().output()
+        )
+        .unwrap()
+    );
+
+    // wrong message
+    let res = <HybridVRF as VRF>::verify("another message", &param, &pk, &proof).unwrap();
+    assert!(res.is_none());
+
+    // verification fails if either component fails
+    let other = <HybridVRF as VRF>::prove("another message", &param, &pk, &sk, seed).unwrap();
+    let mut tampered = proof.clone();
+    tampered.ec = other.ec;
+    assert!(<HybridVRF as VRF>::verify(message, &param, &pk, &tampered)
+        .unwrap()
+        .is_none());
+    let mut tampered = proof.clone();
+    tampered.lb = other.lb;
+    assert!(<HybridVRF as VRF>::verify(message, &param, &pk, &tampered)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_serdes_hybrid() {
+    let seed = [1u8; 32];
+    let param = <HybridVRF as VRF>::paramgen(seed).unwrap();
+    let (pk, sk) = <HybridVRF as VRF>::keygen(seed, &param).unwrap();
+    let message = "this is a message that vrf signs";
+    let proof = <HybridVRF as VRF>::prove(message, &param, &pk, &sk, seed).unwrap();
+
+    let mut buf: Vec<u8> = vec![];
+    assert!(param.serialize(&mut buf).is_ok());
+    let param2 = HybridParam::deserialize(&mut buf[..].as_ref()).unwrap();
+    assert_eq!(param, param2);
+
+    let mut buf: Vec<u8> = vec![];
+    assert!(pk.serialize(&mut buf).is_ok());
+    let pk2 = HybridPublicKey::deserialize(&mut buf[..].as_ref()).unwrap();
+    assert_eq!(pk, pk2);
+
+    let mut buf: Vec<u8> = vec![];
+    assert!(sk.serialize(&mut buf).is_ok());
+    let sk2 = HybridSecretKey::deserialize(&mut buf[..].as_ref()).unwrap();
+    assert_eq!(sk, sk2);
+
+    let mut buf: Vec<u8> = vec![];
+    assert!(proof.serialize(&mut buf).is_ok());
+    let proof2 = HybridProof::deserialize(&mut buf[..].as_ref()).unwrap();
+    assert_eq!(proof, proof2);
+
+    let res = <HybridVRF as VRF>::verify(message, &param2, &pk2, &proof2).unwrap();
+    assert!(res.is_some());
+}
@@ -1,8 +1,14 @@
 // use crate::keypair::{PublicKey, SecretKey};
+use crate::ecvrf::{ECVRFSuite, ECVRF};
+use crate::hybrid::HybridVRF;
+use crate::keypair::MTSecretKey;
 use crate::lbvrf::LBVRF;
-use crate::param::Param;
+use crate::param::{Param, ParameterSet, Standard};
 use crate::serde::Serdes;
 use crate::VRF;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, OnceLock};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 #[test]
 fn test_keygen() {
@@ -11,7 +17,7 @@ fn test_keygen() {
     // let param = Param::init(&mut rng);
 
     let param: Param = <LBVRF as VRF>::paramgen(seed).unwrap();
-    let (_pk, _sk) = <LBVRF as VRF>::keygen(seed, param).unwrap();
+    let (_pk, _sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
 }
 
 #[test]
@@ -21,17 +27,103 @@ fn test_serdes_keygen() {
     // let param = Param::init(&mut rng);
 
     let param: Param = <LBVRF as VRF>::paramgen(seed).unwrap();
-    let (pk, sk) = <LBVRF as VRF>::keygen(seed, param).unwrap();
+    let (pk, sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
 
     let mut buf: Vec<u8> = vec![];
     assert!(pk.serialize(&mut buf).is_ok());
     println!("{:?}", buf);
+    assert_eq!(buf.len(), Standard::PK_LEN);
     let pk2 = <LBVRF as VRF>::PublicKey::deserialize(&mut buf[..].as_ref()).unwrap();
     assert_eq!(pk, pk2);
 
     let mut buf: Vec<u8> = vec![];
     assert!(sk.serialize(&mut buf).is_ok());
     println!("{:02x?}", buf);
+    assert_eq!(buf.len(), Standard::SK_LEN);
     let sk2 = <LBVRF as VRF>::SecretKey::deserialize(&mut buf[..].as_ref()).unwrap();
     assert_eq!(sk, sk2);
 }
+
+fn assert_zeroize_on_drop<T: ZeroizeOnDrop>(_value: &T) {}
+
+// drop the value in place and return the bytes it occupied
+fn bytes_after_drop<T>(value: T) -> Vec<u8> {
+    let mut slot = std::mem::MaybeUninit::new(value);
+    let ptr = slot.as_mut_ptr();
+    unsafe {
+        std::ptr::drop_in_place(ptr);
+        std::slice::from_raw_parts(ptr as *const u8, std::mem::size_of::<T>()).to_vec()
+    }
+}
+
+#[test]
+fn test_secret_key_zeroize() {
+    let seed = [1u8; 32];
+    let param: Param = <LBVRF as VRF>::paramgen(seed).unwrap();
+    let (_pk, mut sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
+    assert!(sk.s.iter().any(|e| e.coeff.iter().any(|f| *f != 0)));
+    // the polynomials are on the heap; the buffer stays allocated after zeroize
+    assert_zeroize_on_drop(&sk);
+    let (ptr, len) = (sk.s.as_ptr(), sk.s.len());
+    sk.zeroize();
+    let s = unsafe { std::slice::from_raw_parts(ptr, len) };
+    assert!(s.iter().all(|e| e.coeff.iter().all(|f| *f == 0)));
+
+    let (_pk, sk) = <ECVRF as VRF>::keygen(seed, &ECVRFSuite::Tai).unwrap();
+    assert!(bytes_after_drop(sk).iter().all(|e| *e == 0));
+
+    let param = <HybridVRF as VRF>::paramgen(seed).unwrap();
+    let (_pk, mut sk) = <HybridVRF as VRF>::keygen(seed, &param).unwrap();
+    assert_zeroize_on_drop(&sk);
+    let (ptr, len) = (sk.lb.s.as_ptr(), sk.lb.s.len());
+    sk.zeroize();
+    assert_eq!(sk.ec.seed, [0u8; 32]);
+    let s = unsafe { std::slice::from_raw_parts(ptr, len) };
+    assert!(s.iter().all(|e| e.coeff.iter().all(|f| *f == 0)));
+
+    // only the seed of a many time key is secret
+    let sk = MTSecretKey {
+        seed,
+        height: 1,
+        next_leaf: Arc::new(AtomicUsize::new(0)),
+        nodes: Arc::new(OnceLock::new()),
+    };
+    let mut slot = std::mem::MaybeUninit::new(sk);
+    let ptr = slot.as_mut_ptr();
+    let seed_after_drop = unsafe {
+        std::ptr::drop_in_place(ptr);
+        std::ptr::read(std::ptr::addr_of!((*ptr).seed))
+    };
+    assert_eq!(seed_after_drop, [0u8; 32]);
+}
+
+#[test]
+fn test_secret_key_debug_eq() {
+    let seed = [1u8; 32];
+    let param = <HybridVRF as VRF>::paramgen(seed).unwrap();
+    let (_pk, sk) = <HybridVRF as VRF>::keygen(seed, &param).unwrap();
+    let (_pk, other) = <HybridVRF as VRF>::keygen([2u8; 32], &param).unwrap();
+
+    // the secrets are never printed
+    assert_eq!(format!("{:?}", sk.lb), "SecretKey { .. }");
+    assert_eq!(format!("{:?}", sk.ec), "ECSecretKey { .. }");
+    assert_eq!(format!("{:?}", sk), "HybridSecretKey { .. }");
+    let mt = MTSecretKey {
+        seed,
+        height: 1,
+        next_leaf: Arc::new(AtomicUsize::new(0)),
+        nodes: Arc::new(OnceLock::new()),
+    };
+    assert_eq!(
+        format!("{:?}", mt),
+        "MTSecretKey { height: 1, next_leaf: 0, .. }"
+    );
+
+    assert_eq!(sk, sk.clone());
+    assert_ne!(sk.lb, other.lb);
+    assert_ne!(sk.ec, other.ec);
+    assert_ne!(sk, other);
+    let mut lb = sk.lb.clone();
+    lb.s.pop();
+    assert_ne!(sk.lb, lb);
+}
@@ -1,18 +1,20 @@
+use crate::keypair::PublicKey;
 use crate::lbvrf::*;
 use crate::param::*;
 use crate::rand::RngCore;
 use crate::serde::Serdes;
+use crate::Error;
 use crate::VRF;
 #[test]
 fn test_param_gen() {
-    let p = LBVRF::paramgen([0; 32]);
+    let p = <LBVRF as VRF>::paramgen([0; 32]);
     println!("{:?}", p);
 }
 
 #[test]
 fn test_hash_to_challenge() {
     let input = "this is a random input for testing";
-    let c = hash_to_challenge(input.as_ref());
+    let c = hash_to_challenge::<Standard>(input.as_ref()).unwrap();
     let mut sum = 0;
     for e in c.coeff.iter() {
         assert!(*e <= 1 || *e >= -1, "coefficients out of range {}", *e);
@@ -20,7 +22,19 @@ fn test_hash_to_challenge() {
             sum += 1;
         }
     }
-    assert_eq!(sum, KAPPA)
+    assert_eq!(sum, KAPPA);
+
+    // the weight is exact, even if a position is drawn several times
+    for i in 0..2000u32 {
+        let input = i.to_be_bytes();
+        let c = hash_to_challenge::<Standard>(&input).unwrap();
+        assert_eq!(c.coeff.iter().filter(|e| **e != 0).count(), KAPPA);
+        let c = hash_to_challenge::<HighSecurity>(&input).unwrap();
+        assert_eq!(
+            c.coeff.iter().filter(|e| **e != 0).count(),
+            HighSecurity::KAPPA
+        );
+    }
 }
 
 #[test]
@@ -30,18 +44,19 @@ fn test_lbvrf() {
     // let param = Param::init(&mut rng);
 
     let param: Param = <LBVRF as VRF>::paramgen(seed).unwrap();
-    let (pk, sk) = <LBVRF as VRF>::keygen(seed, param).unwrap();
+    let (pk, sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
     let message = "this is a message that vrf signs";
     let seed = [0u8; 32];
-    let proof = <LBVRF as VRF>::prove(message, param, pk, sk, seed).unwrap();
+    let proof = <LBVRF as VRF>::prove(message, &param, &pk, &sk, seed).unwrap();
 
     let mut buf: Vec<u8> = vec![];
     assert!(proof.serialize(&mut buf).is_ok());
     println!("{:?}", buf);
+    assert_eq!(buf.len(), Standard::PROOF_LEN);
     let proof2 = <LBVRF as VRF>::Proof::deserialize(&mut buf[..].as_ref()).unwrap();
     assert_eq!(proof, proof2);
 
-    let res = <LBVRF as VRF>::verify(message, param, pk, proof).unwrap();
+    let res = <LBVRF as VRF>::verify(message, &param, &pk, &proof).unwrap();
     assert!(res.is_some());
     assert_eq!(res.unwrap(), proof.v);
 }
@@ -60,14 +75,337 @@ fn test_rs() {
         rng.fill_bytes(&mut key_seed);
         rng.fill_bytes(&mut vrf_seed);
         let param: Param = <LBVRF as VRF>::paramgen(pp_seed).unwrap();
-        let (pk, sk) = <LBVRF as VRF>::keygen(key_seed, param).unwrap();
+        let (pk, sk) = <LBVRF as VRF>::keygen(key_seed, &param).unwrap();
         let message = "this is a message that vrf signs";
-        let (proof, rs) = prove_with_rs(message, param, pk, sk, vrf_seed).unwrap();
+        let (proof, rs) = prove_with_rs(message, &param, &pk, &sk, vrf_seed).unwrap();
         t += rs;
-        let res = <LBVRF as VRF>::verify(message, param, pk, proof).unwrap();
+        let res = <LBVRF as VRF>::verify(message, &param, &pk, &proof).unwrap();
         assert!(res.is_some());
         assert_eq!(res.unwrap(), proof.v);
     }
     println!("rs times {} for {} vrfs", t, total);
     // assert!(false)
 }
+
+#[test]
+fn test_prove_window() {
+    // the proof does not depend on how many attempts are evaluated at once
+    let param: Param = <LBVRF as VRF>::paramgen([0u8; 32]).unwrap();
+    let (pk, sk) = <LBVRF as VRF>::keygen([1u8; 32], &param).unwrap();
+    for i in 0..8u8 {
+        let message = [i; 32];
+        let expected = prove_with_window(message, &param, &pk, &sk, [i; 32], 1).unwrap();
+        assert_eq!(
+            prove_with_rs(message, &param, &pk, &sk, [i; 32]).unwrap(),
+            expected
+        );
+        for window in [2, 3, 8].iter() {
+            let res = prove_with_window(message, &param, &pk, &sk, [i; 32], *window).unwrap();
+            assert_eq!(res, expected);
+        }
+    }
+}
+
+#[test]
+fn test_lbvrf_errors() {
+    let seed = [0u8; 32];
+    let param: Param = <LBVRF as VRF>::paramgen(seed).unwrap();
+    let (pk, sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
+    let message = "this is a message that vrf signs";
+    let proof = <LBVRF as VRF>::prove(message, &param, &pk, &sk, seed).unwrap();
+
+    // wrong message
+    let res = verify_with_reason("another message", &param, &pk, &proof);
+    assert!(matches!(res, Err(Error::ChallengeMismatch)));
+
+    // z out of bound
+    let mut proof2 = proof.clone();
+    proof2.z[0].coeff[0] = BETA;
+    let res = verify_with_reason(message, &param, &pk, &proof2);
+    assert!(matches!(res, Err(Error::NormBound)));
+    let res = <LBVRF as VRF>::verify(message, &param, &pk, &proof2).unwrap();
+    assert!(res.is_none());
+
+    // truncated encoding
+    let mut buf: Vec<u8> = vec![];
+    assert!(proof.serialize(&mut buf).is_ok());
+    let res = <Proof>::deserialize(&mut buf[..buf.len() - 1].as_ref());
+    assert!(matches!(res, Err(Error::Io(_))));
+
+    // non-canonical encodings of z, c and v
+    let z_len = Standard::L * 32 * Standard::BETA_BITS;
+    for start in [0, z_len, z_len + Standard::CHALLENGE_LEN].iter() {
+        let mut buf2 = buf.clone();
+        for e in buf2[*start..*start + 11].iter_mut() {
+            *e = 0xff;
+        }
+        let res = <Proof>::deserialize(&mut buf2[..].as_ref());
+        assert!(matches!(res, Err(Error::MalformedEncoding(_))));
+    }
+
+    // non-canonical keys; t follows the param digest
+    let mut buf: Vec<u8> = vec![];
+    assert!(pk.serialize(&mut buf).is_ok());
+    buf[32] = 0xff;
+    let res = <PublicKey>::deserialize(&mut buf[..].as_ref());
+    assert!(matches!(res, Err(Error::MalformedEncoding(_))));
+    let mut buf: Vec<u8> = vec![];
+    assert!(sk.serialize(&mut buf).is_ok());
+    buf[0] = 0xff;
+    let res = <crate::keypair::SecretKey>::deserialize(&mut buf[..].as_ref());
+    assert!(matches!(res, Err(Error::MalformedEncoding(_))));
+}
+
+#[test]
+fn test_param_mismatch() {
+    let seed = [0u8; 32];
+    let param: Param = <LBVRF as VRF>::paramgen(seed).unwrap();
+    let other: Param = <LBVRF as VRF>::paramgen([1u8; 32]).unwrap();
+    let (pk, sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
+    let message = "this is a message that vrf signs";
+    let proof = <LBVRF as VRF>::prove(message, &param, &pk, &sk, seed).unwrap();
+    assert_eq!(pk.param_digest(), param.digest);
+
+    // the key is rejected with another param, rather than the proof
+    let res = <LBVRF as VRF>::verify(message, &other, &pk, &proof);
+    assert!(matches!(res, Err(Error::ParamMismatch)));
+    let res = <LBVRF as VRF>::prove(message, &other, &pk, &sk, seed);
+    assert!(matches!(res, Err(Error::ParamMismatch)));
+    assert!(matches!(
+        PreparedVerifier::new(&other, &pk),
+        Err(Error::ParamMismatch)
+    ));
+    let res = verify_batch(&other, &[(message, &pk, &proof), (message, &pk, &proof)]);
+    assert!(matches!(res[0], Err(Error::ParamMismatch)));
+    assert!(matches!(res[1], Err(Error::ParamMismatch)));
+
+    // the digest is part of the encoding
+    let mut buf: Vec<u8> = vec![];
+    pk.serialize(&mut buf).unwrap();
+    assert_eq!(&buf[0..32], &param.digest[..]);
+    buf[0..32].copy_from_slice(&other.digest);
+    let pk2 = <PublicKey>::deserialize(&mut buf[..].as_ref()).unwrap();
+    let res = <LBVRF as VRF>::verify(message, &param, &pk2, &proof);
+    assert!(matches!(res, Err(Error::ParamMismatch)));
+
+    // the fingerprint depends on the key, its param and its set
+    let fingerprint = pk.fingerprint().unwrap();
+    assert_eq!(fingerprint, pk.clone().fingerprint().unwrap());
+    assert_ne!(fingerprint, pk2.fingerprint().unwrap());
+    let (pk3, _sk3) = <LBVRF as VRF>::keygen([1u8; 32], &param).unwrap();
+    assert_ne!(fingerprint, pk3.fingerprint().unwrap());
+    let high = Param::<HighSecurity>::from_seed(seed);
+    assert_ne!(high.digest, param.digest);
+    let (pk4, _sk4) = LBVRF::<HighSecurity>::keygen(seed, &high).unwrap();
+    assert_ne!(fingerprint, pk4.fingerprint().unwrap());
+}
+
+#[test]
+fn test_param_set_mismatch() {
+    let seed = [0u8; 32];
+    let param = Param::<Standard>::from_seed(seed);
+    let high = Param::<HighSecurity>::from_seed(seed);
+    let (pk, sk) = LBVRF::<Standard>::keygen(seed, &param).unwrap();
+    let (high_pk, _high_sk) = LBVRF::<HighSecurity>::keygen(seed, &high).unwrap();
+    let message = "this is a message that vrf signs";
+    let proof = LBVRF::<Standard>::prove(message, &param, &pk, &sk, seed).unwrap();
+
+    // the first rows of a high security key decode as a standard key,
+    // which is bound to the high security param
+    let mut buf: Vec<u8> = vec![];
+    high_pk.serialize(&mut buf).unwrap();
+    let pk2 = <PublicKey<Standard>>::deserialize(&mut buf[..].as_ref()).unwrap();
+    assert_eq!(pk2.param_digest(), high.digest);
+    let res = LBVRF::<Standard>::verify(message, &param, &pk2, &proof);
+    assert!(matches!(res, Err(Error::ParamMismatch)));
+    let res = LBVRF::<Standard>::prove(message, &param, &pk2, &sk, seed);
+    assert!(matches!(res, Err(Error::ParamMismatch)));
+}
+
+#[test]
+fn test_prepared_verifier() {
+    let seed = [0u8; 32];
+    let param: Param = <LBVRF as VRF>::paramgen(seed).unwrap();
+    let (pk, sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
+    let verifier = PreparedVerifier::new(&param, &pk).unwrap();
+
+    for i in 0..4u8 {
+        let message = [i; 32];
+        let proof = <LBVRF as VRF>::prove(message, &param, &pk, &sk, [i; 32]).unwrap();
+        let res = verifier.verify(message, &proof).unwrap();
+        assert_eq!(res, Some(proof.v));
+        assert_eq!(
+            res,
+            <LBVRF as VRF>::verify(message, &param, &pk, &proof).unwrap()
+        );
+
+        let res = verifier.verify([i + 1; 32], &proof).unwrap();
+        assert!(res.is_none());
+    }
+}
+
+#[test]
+fn test_verify_batch() {
+    let param: Param = <LBVRF as VRF>::paramgen([0u8; 32]).unwrap();
+    let mut keys = vec![];
+    for i in 0..3u8 {
+        keys.push(<LBVRF as VRF>::keygen([i; 32], &param).unwrap());
+    }
+    let mut batch = vec![];
+    for i in 0..6u8 {
+        // the keys are shared by several proofs
+        let (pk, sk) = &keys[i as usize % 3];
+        let message = vec![i; 32];
+        let proof = <LBVRF as VRF>::prove(&message, &param, pk, sk, [i; 32]).unwrap();
+        batch.push((message, pk, proof));
+    }
+    // wrong message, z out of bound and wrong public key
+    batch[1].0[0] ^= 1;
+    batch[2].2.z[0].coeff[0] = BETA;
+    batch[3].1 = &keys[1].0;
+    // a copy of a key is the same key
+    let copy = keys[2].0.clone();
+    batch[5].1 = &copy;
+
+    let items: Vec<_> = batch.iter().map(|(m, pk, p)| (m, *pk, p)).collect();
+    let res = verify_batch(&param, &items);
+    assert_eq!(res.len(), batch.len());
+    for ((message, pk, proof), r) in batch.iter().zip(res.iter()) {
+        match verify_with_reason(message, &param, pk, proof) {
+            Ok(v) => assert_eq!(r.as_ref().unwrap(), &v),
+            Err(e) => assert_eq!(r.as_ref().unwrap_err().to_string(), e.to_string()),
+        }
+    }
+    assert!(res[0].is_ok() && res[4].is_ok() && res[5].is_ok());
+    assert!(matches!(res[1], Err(Error::ChallengeMismatch)));
+    assert!(matches!(res[2], Err(Error::NormBound)));
+    assert!(matches!(res[3], Err(Error::ChallengeMismatch)));
+
+    let empty: Vec<(&[u8], &PublicKey, &Proof)> = vec![];
+    assert!(verify_batch(&param, &empty).is_empty());
+}
+
+#[test]
+fn test_proof_to_hash() {
+    let seed = [0u8; 32];
+    let param: Param = <LBVRF as VRF>::paramgen(seed).unwrap();
+    let (pk, sk) = <LBVRF as VRF>::keygen(seed, &param).unwrap();
+    let message = "this is a message that vrf signs";
+    let proof = <LBVRF as VRF>::prove(message, &param, &pk, &sk, seed).unwrap();
+
+    let hash = proof.proof_to_hash().unwrap();
+    assert_eq!(hash, output_to_hash(&proof.output()).unwrap());
+    assert_eq!(hash[0..32], output_to_hash32(&proof.v).unwrap());
+
+    let res = verify_to_hash(message, &param, &pk, &proof).unwrap();
+    assert_eq!(res, Some(hash));
+    let res = verify_to_hash("another message", &param, &pk, &proof).unwrap();
+    assert!(res.is_none());
+
+    // the output does not depend on the randomness used by the prover
+    let proof2 = <LBVRF as VRF>::prove(message, &param, &pk, &sk, [1u8; 32]).unwrap();
+    assert_eq!(proof2.proof_to_hash().unwrap(), hash);
+
+    let proof3 = <LBVRF as VRF>::prove("another message", &param, &pk, &sk, seed).unwrap();
+    assert_ne!(proof3.proof_to_hash().unwrap(), hash);
+}
+
+#[test]
+fn test_lbvrf_high_security() {
+    type Vrf = LBVRF<HighSecurity>;
+    let seed = [3u8; 32];
+    let param = <Vrf as VRF>::paramgen(seed).unwrap();
+    assert_eq!(param.matrix.len(), HighSecurity::K);
+    assert!(param.matrix.iter().all(|e| e.len() == HighSecurity::L));
+    let (pk, sk) = <Vrf as VRF>::keygen(seed, &param).unwrap();
+    let message = "this is a message that vrf signs";
+    let proof = <Vrf as VRF>::prove(message, &param, &pk, &sk, seed).unwrap();
+    let res = <Vrf as VRF>::verify(message, &param, &pk, &proof).unwrap();
+    assert_eq!(res, Some(proof.v));
+    let res = <Vrf as VRF>::verify("another message", &param, &pk, &proof).unwrap();
+    assert!(res.is_none());
+
+    // the encodings follow the dimensions of the set
+    let mut buf: Vec<u8> = vec![];
+    pk.serialize(&mut buf).unwrap();
+    assert_eq!(buf.len(), 32 + HighSecurity::K * Q_POLY_LEN);
+    assert_eq!(buf.len(), HighSecurity::PK_LEN);
+    let mut buf: Vec<u8> = vec![];
+    proof.serialize(&mut buf).unwrap();
+    assert_eq!(buf.len(), HighSecurity::L * 32 * 19 + 32 + 8 + P_POLY_LEN);
+    assert_eq!(buf.len(), HighSecurity::PROOF_LEN);
+    let proof2 = <Vrf as VRF>::Proof::deserialize(&mut buf[..].as_ref()).unwrap();
+    assert_eq!(proof, proof2);
+    // a standard proof is shorter, and the 18 bits slices of z are out of range
+    assert!(matches!(
+        <Proof>::deserialize(&mut buf[..].as_ref()),
+        Err(Error::MalformedEncoding(_))
+    ));
+    assert!(<Proof<HighSecurity>>::deserialize(&mut buf[..buf.len() - 1].as_ref()).is_err());
+
+    let c = hash_to_challenge::<HighSecurity>(b"input").unwrap();
+    assert_eq!(
+        c.coeff.iter().filter(|e| **e != 0).count(),
+        HighSecurity::KAPPA
+    );
+}
+
+// a set whose challenges run out of hash output for about 40% of the inputs
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Dense;
+
+impl ParameterSet for Dense {
+    const NAME: &'static str = "dense";
+    const ID: u8 = 0xff;
+    const K: usize = 1;
+    const L: usize = 1;
+    const KAPPA: usize = 87;
+    const BETA: i32 = 87 * 256;
+}
+
+#[test]
+fn test_entropy_exhausted() {
+    let exhausted = (0..100u32)
+        .filter(|i| {
+            matches!(
+                hash_to_challenge::<Dense>(&i.to_be_bytes()),
+                Err(Error::EntropyExhausted)
+            )
+        })
+        .count();
+    assert!(exhausted > 0 && exhausted < 100);
+
+    // the prover resamples y instead of failing
+    let param = Param::<Dense>::from_seed([0u8; 32]);
+    let (pk, sk) = LBVRF::<Dense>::keygen([1u8; 32], &param).unwrap();
+    for i in 0..8u8 {
+        let proof = LBVRF::<Dense>::prove([i], &param, &pk, &sk, [i; 32]).unwrap();
+        let res = LBVRF::<Dense>::verify([i], &param, &pk, &proof).unwrap();
+        assert!(res.is_some());
+    }
+}
+
+// a set whose challenges nearly always run out of hash output
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Heavy;
+
+impl ParameterSet for Heavy {
+    const NAME: &'static str = "heavy";
+    const ID: u8 = 0xfe;
+    const K: usize = 1;
+    const L: usize = 1;
+    const KAPPA: usize = 140;
+    const BETA: i32 = 140 * 256;
+}
+
+#[test]
+fn test_hash_to_challenge_bounds() {
+    // the positions may catch up with the signs at any point, which must
+    // fail cleanly rather than read past the hash output
+    for i in 0..2000u32 {
+        match hash_to_challenge::<Heavy>(&i.to_be_bytes()) {
+            Ok(c) => assert_eq!(c.coeff.iter().filter(|e| **e != 0).count(), Heavy::KAPPA),
+            Err(e) => assert!(matches!(e, Error::EntropyExhausted)),
+        }
+    }
+}
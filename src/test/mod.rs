@@ -1,5 +1,14 @@
+#[cfg(target_arch = "x86_64")]
+mod avx2;
+mod ct;
+mod ecvrf;
+mod envelope;
+mod estimate;
+mod hybrid;
 mod keypair;
 mod lbvrf;
+mod mtlbvrf;
 mod param;
 mod poly256;
 mod poly32;
+mod serde;
@@ -0,0 +1,141 @@
+use crate::keypair::MTSecretKey;
+use crate::lbvrf::LBVRF;
+use crate::mtlbvrf::*;
+use crate::serde::Serdes;
+use crate::Error;
+use crate::VRF;
+
+fn small_param(seed: [u8; 32]) -> MTParam {
+    let param = <LBVRF as VRF>::paramgen(seed).unwrap();
+    MTParam::new(param, 2).unwrap()
+}
+
+#[test]
+fn test_mtlbvrf() {
+    let seed = [0u8; 32];
+    let param = small_param(seed);
+    let (pk, sk) = <MTLBVRF as VRF>::keygen(seed, &param).unwrap();
+    let message = "this is a message that vrf signs";
+
+    for i in 0..4 {
+        assert_eq!(sk.next_leaf(), i);
+        let input = epoch_input(i as u64, message);
+        let proof = <MTLBVRF as VRF>::prove(&input, &param, &pk, &sk, seed).unwrap();
+        assert_eq!(proof.index(), i);
+
+        let mut buf: Vec<u8> = vec![];
+        assert!(proof.serialize(&mut buf).is_ok());
+        let proof2 = <MTLBVRF as VRF>::Proof::deserialize(&mut buf[..].as_ref()).unwrap();
+        assert_eq!(proof, proof2);
+
+        let res = <MTLBVRF as VRF>::verify(&input, &param, &pk, &proof).unwrap();
+        assert_eq!(res, Some(proof.output()));
+        assert_eq!(
+            proof.proof_to_hash().unwrap(),
+            proof.proof.proof_to_hash().unwrap()
+        );
+
+        // wrong message
+        let res = <MTLBVRF as VRF>::verify(
+            epoch_input(i as u64, "another message"),
+            &param,
+            &pk,
+            &proof,
+        )
+        .unwrap();
+        assert!(res.is_none());
+
+        // wrong leaf index
+        let mut proof3 = proof;
+        proof3.index ^= 1;
+        let res = <MTLBVRF as VRF>::verify(&input, &param, &pk, &proof3).unwrap();
+        assert!(res.is_none());
+    }
+
+    // all leaves are used
+    assert_eq!(sk.remaining(), 0);
+    let res = <MTLBVRF as VRF>::prove(epoch_input(4, message), &param, &pk, &sk, seed);
+    assert!(matches!(res, Err(Error::KeyExhausted)));
+}
+
+#[test]
+fn test_mtlbvrf_uniqueness() {
+    let seed = [2u8; 32];
+    let param = small_param(seed);
+    let (pk, sk) = <MTLBVRF as VRF>::keygen(seed, &param).unwrap();
+    let message = "this is a message that vrf signs";
+
+    // a proof under another leaf is rejected for the same message
+    let proof = <MTLBVRF as VRF>::prove(epoch_input(1, message), &param, &pk, &sk, seed).unwrap();
+    assert_eq!(proof.index(), 1);
+    let res = <MTLBVRF as VRF>::verify(epoch_input(0, message), &param, &pk, &proof).unwrap();
+    assert!(res.is_none());
+    let mut proof2 = proof.clone();
+    proof2.index = 0;
+    let res = <MTLBVRF as VRF>::verify(epoch_input(0, message), &param, &pk, &proof2).unwrap();
+    assert!(res.is_none());
+
+    // the epochs are used in increasing order, the skipped leaf is burnt
+    assert_eq!(sk.next_leaf(), 2);
+    assert_eq!(sk.remaining(), 2);
+    for epoch in [0, 1].iter() {
+        let res = <MTLBVRF as VRF>::prove(epoch_input(*epoch, message), &param, &pk, &sk, seed);
+        assert!(matches!(res, Err(Error::EpochUsed)));
+    }
+    assert_eq!(sk.next_leaf(), 2);
+
+    // the input starts with the epoch
+    let res = <MTLBVRF as VRF>::prove([0u8; 7], &param, &pk, &sk, seed);
+    assert!(matches!(res, Err(Error::MalformedEncoding(_))));
+    let res = <MTLBVRF as VRF>::verify([0u8; 7], &param, &pk, &proof);
+    assert!(matches!(res, Err(Error::MalformedEncoding(_))));
+}
+
+#[test]
+fn test_mtlbvrf_key_state() {
+    let seed = [1u8; 32];
+    let param = small_param(seed);
+    let (pk, sk) = <MTLBVRF as VRF>::keygen(seed, &param).unwrap();
+    let message = "this is a message that vrf signs";
+
+    let proof = <MTLBVRF as VRF>::prove(epoch_input(0, message), &param, &pk, &sk, seed).unwrap();
+    assert_eq!(proof.index(), 0);
+
+    // the state survives serialization and the tree is rebuilt on demand
+    let mut buf: Vec<u8> = vec![];
+    assert!(sk.serialize(&mut buf).is_ok());
+    let sk2 = MTSecretKey::deserialize(&mut buf[..].as_ref()).unwrap();
+    assert_eq!(sk, sk2);
+    assert_eq!(sk2.next_leaf(), 1);
+    let res = <MTLBVRF as VRF>::prove(epoch_input(0, message), &param, &pk, &sk2, seed);
+    assert!(matches!(res, Err(Error::EpochUsed)));
+
+    let input = epoch_input(1, message);
+    let proof = <MTLBVRF as VRF>::prove(&input, &param, &pk, &sk2, seed).unwrap();
+    assert_eq!(proof.index(), 1);
+    let res = <MTLBVRF as VRF>::verify(&input, &param, &pk, &proof).unwrap();
+    assert!(res.is_some());
+
+    // the counter is shared between clones
+    assert_eq!(sk2.next_leaf(), 2);
+    let sk3 = sk2.clone();
+    let proof = <MTLBVRF as VRF>::prove(epoch_input(2, message), &param, &pk, &sk3, seed).unwrap();
+    assert_eq!(proof.index(), 2);
+    assert_eq!(sk2.next_leaf(), 3);
+}
+
+#[test]
+fn test_serdes_mtparam() {
+    let seed = [0u8; 32];
+    let param = small_param(seed);
+    let mut buf: Vec<u8> = vec![];
+    assert!(param.serialize(&mut buf).is_ok());
+    let param2 = MTParam::deserialize(&mut buf[..].as_ref()).unwrap();
+    assert_eq!(param, param2);
+
+    assert!(matches!(
+        MTParam::new(param.param.clone(), 0),
+        Err(Error::InvalidParam(_))
+    ));
+    assert!(MTParam::new(param.param, MT_MAX_HEIGHT + 1).is_err());
+}
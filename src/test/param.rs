@@ -1,12 +1,13 @@
 use crate::lbvrf::LBVRF;
-use crate::param::Param;
+use crate::param::{HighSecurity, Param, Q};
 use crate::serde::Serdes;
+use crate::Error;
 use crate::VRF;
 
 #[test]
 fn test_param() {
     let mut rng = rand::thread_rng();
-    let _param = Param::init(&mut rng);
+    let _param: Param = Param::init(&mut rng);
 }
 
 #[test]
@@ -22,3 +23,88 @@ fn test_serdes_param() {
     let param2 = Param::deserialize(&mut buf[..].as_ref()).unwrap();
     assert_eq!(param, param2);
 }
+
+#[test]
+fn test_param_from_seed() {
+    let seed = [7u8; 32];
+    let param: Param = Param::from_seed(seed);
+    assert_eq!(param.seed(), Some(seed));
+    assert_eq!(param, Param::from_seed(seed));
+    assert_ne!(param, Param::from_seed([8u8; 32]));
+
+    // the sets are unrelated, rather than a submatrix of one another
+    let high = Param::<HighSecurity>::from_seed(seed);
+    assert_ne!(high.digest, param.digest);
+    for (e, f) in param.matrix.iter().zip(high.matrix.iter()) {
+        for (g, h) in e.iter().zip(f.iter()) {
+            assert_ne!(g, h);
+        }
+    }
+    for e in param.matrix.iter() {
+        for f in e.iter() {
+            for g in f.coeff.iter() {
+                assert!(*g >= 0 && *g < Q)
+            }
+        }
+    }
+
+    // a seeded param is encoded as its seed only
+    let mut buf: Vec<u8> = vec![];
+    assert!(param.serialize(&mut buf).is_ok());
+    assert_eq!(buf.len(), 33);
+    let param2 = Param::deserialize(&mut buf[..].as_ref()).unwrap();
+    assert_eq!(param, param2);
+}
+
+#[test]
+fn test_serdes_full_param() {
+    let mut rng = rand::thread_rng();
+    let param: Param = Param::init(&mut rng);
+    assert!(param.seed().is_none());
+
+    let mut buf: Vec<u8> = vec![];
+    assert!(param.serialize(&mut buf).is_ok());
+    let param2 = Param::deserialize(&mut buf[..].as_ref()).unwrap();
+    assert_eq!(param, param2);
+
+    // the digest must match the matrix
+    let last = buf.len() - 1;
+    buf[last] ^= 1;
+    let res = <Param>::deserialize(&mut buf[..].as_ref());
+    assert!(matches!(res, Err(Error::MalformedEncoding(_))));
+
+    // unknown mode
+    buf[0] = 2;
+    let res = <Param>::deserialize(&mut buf[..].as_ref());
+    assert!(matches!(res, Err(Error::MalformedEncoding(_))));
+}
+
+#[test]
+fn test_parameter_sets() {
+    use crate::param::*;
+    // the derived constants of the standard set match the original ones
+    assert_eq!(Standard::BETA_M2_P1, BETA_M2_P1);
+    assert_eq!(Standard::BETA_M_KAPPA, BETA_M_KAPPA);
+    assert_eq!(Standard::BETA_RS_RANGE, BETA_RS_RANGE);
+    assert_eq!(Standard::BETA_BITS, 18);
+    assert_eq!(Q_POLY_LEN, 864);
+    assert_eq!(P_POLY_LEN, 88);
+    assert_eq!(Standard::PK_LEN, 32 + 4 * 864);
+    assert_eq!(Standard::SK_LEN, 576);
+    assert_eq!(Standard::PROOF_LEN, 9 * 576 + 37 + 88);
+    assert_eq!(HighSecurity::BETA_M2_P1, 2 * HighSecurity::BETA as u32 + 1);
+
+    fn check<S: ParameterSet>() {
+        // a proof is accepted with probability about 1/e
+        assert_eq!(S::BETA as usize, S::KAPPA * 256 * S::L);
+        // y is uniform within the symmetric range [-BETA, BETA]
+        assert_eq!(S::BETA_M2_P1, 2 * S::BETA as u32 + 1);
+        assert_eq!(S::BETA_RS_RANGE % S::BETA_M2_P1, 0);
+        assert!(S::BETA_RS_RANGE.checked_add(S::BETA_M2_P1).is_none());
+        assert!(1u32 << S::BETA_BITS > 2 * S::BETA as u32);
+        assert!(1u32 << (S::BETA_BITS - 1) <= 2 * S::BETA as u32);
+        assert!(S::K < S::L);
+    }
+    check::<Standard>();
+    check::<HighSecurity>();
+}
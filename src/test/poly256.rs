@@ -1,8 +1,10 @@
-use crate::param::BETA;
-use crate::param::Q;
-use crate::poly::PolyArith;
-use crate::poly256::poly256_inner_product;
+use crate::param::{BETA, BETA_M2_P1, BETA_RS_RANGE, P_RS_RANGE, Q_RS_RANGE};
+use crate::param::{Q, Q_POLY_LEN};
+use crate::poly::{PolyArith, ShortSample, UniformSample};
 use crate::poly256::Poly256;
+use crate::poly256::{
+    poly256_inner_product, poly256_inner_product_ntt, rand_mod_bound, school_book,
+};
 use crate::poly32::Poly32;
 use crate::serde::Serdes;
 use std::convert::Into;
@@ -10,7 +12,7 @@ use std::convert::Into;
 #[test]
 fn test_rand_mod_q() {
     let mut rng = rand::thread_rng();
-    let a: Poly256 = PolyArith::uniform_random(&mut rng);
+    let a: Poly256 = UniformSample::uniform_random(&mut rng);
     for e in a.coeff.iter() {
         assert!(*e < Poly256::MODULUS, "coefficient greater than Q")
     }
@@ -19,19 +21,16 @@ fn test_rand_mod_q() {
 #[test]
 fn test_rand_mod_beta() {
     let mut rng = rand::thread_rng();
-    let a: Poly256 = PolyArith::rand_mod_beta(&mut rng);
+    let a: Poly256 = ShortSample::rand_mod_beta(&mut rng);
     for e in a.coeff.iter() {
-        assert!(
-            *e <= BETA as i64 && *e >= -(BETA as i64),
-            "coefficient greater than beta"
-        )
+        assert!(*e <= BETA && *e >= -BETA, "coefficient greater than beta")
     }
 }
 
 #[test]
 fn test_rand_trinary() {
     let mut rng = rand::thread_rng();
-    let a: Poly256 = PolyArith::rand_trinary(&mut rng);
+    let a: Poly256 = ShortSample::rand_trinary(&mut rng);
     for e in a.coeff.iter() {
         assert!(*e <= 1 && *e >= -1, "coefficient not trinary")
     }
@@ -71,6 +70,63 @@ fn test_poly256_mul() {
     }
 }
 
+#[test]
+fn test_poly256_ntt_mul() {
+    let mut rng = rand::thread_rng();
+
+    // uniform * uniform
+    let a = Poly256::uniform_random(&mut rng);
+    let b = Poly256::uniform_random(&mut rng);
+    let c = Poly256::mul(&a, &b);
+    let mut d = school_book(&a, &b);
+    d.normalized();
+    assert_eq!(c, d);
+
+    // uniform * centered short polynomial
+    let b = Poly256::rand_mod_beta(&mut rng);
+    let c = Poly256::mul(&a, &b);
+    let mut d = school_book(&a, &b);
+    d.normalized();
+    assert_eq!(c, d);
+
+    // uniform * trinary
+    let b = Poly256::rand_trinary(&mut rng);
+    let c = Poly256::mul(&a, &b);
+    let mut d = school_book(&a, &b);
+    d.normalized();
+    assert_eq!(c, d);
+
+    // x^255 * x = -1
+    let mut a = Poly256::zero();
+    a.coeff[255] = 1;
+    let mut b = Poly256::zero();
+    b.coeff[1] = 1;
+    let c = Poly256::mul(&a, &b);
+    assert_eq!(c.coeff[0], Q - 1);
+    for i in 1..Poly256::DEGREE {
+        assert!(c.coeff[i] == 0)
+    }
+}
+
+#[test]
+fn test_poly256_ntt_inner_prod() {
+    let mut rng = rand::thread_rng();
+    let a: Vec<Poly256> = (0..9).map(|_| Poly256::uniform_random(&mut rng)).collect();
+    let b: Vec<Poly256> = (0..9).map(|_| Poly256::rand_mod_beta(&mut rng)).collect();
+
+    let mut expected = Poly256::zero();
+    for i in 0..9 {
+        expected.add_assign(&school_book(&a[i], &b[i]));
+    }
+    expected.normalized();
+
+    assert_eq!(poly256_inner_product(&a, &b), expected);
+
+    let a_ntt: Vec<Poly256> = a.iter().map(|x| x.ntt()).collect();
+    let b_ntt: Vec<Poly256> = b.iter().map(|x| x.ntt()).collect();
+    assert_eq!(poly256_inner_product_ntt(&a_ntt, &b_ntt), expected);
+}
+
 #[test]
 fn test_poly256_inner_prod() {
     let mut a = Poly256::zero();
@@ -78,17 +134,14 @@ fn test_poly256_inner_prod() {
     a.coeff[1] = -1;
     let vec_a = [a; 4];
     let vec_b = [a; 4];
-    let c = poly256_inner_product(vec_a.as_ref(), &vec_b.as_ref());
+    let c = poly256_inner_product(vec_a.as_ref(), vec_b.as_ref());
     assert!(c.coeff[0] == 4);
     assert!(c.coeff[1] == Q - 8);
     assert!(c.coeff[2] == 4);
     for i in 3..Poly256::DEGREE {
         assert!(c.coeff[i] == 0)
     }
-    println!(
-        "{:?}",
-        a.coeff.iter().map(|x| *x as i32).collect::<Vec<i32>>()
-    );
+    println!("{:?}", a.coeff.to_vec());
 }
 
 #[test]
@@ -102,17 +155,20 @@ fn test_poly256_serdes() {
 
     // random poly
     let mut rng = rand::thread_rng();
-    let a: Poly256 = PolyArith::uniform_random(&mut rng);
+    let a: Poly256 = UniformSample::uniform_random(&mut rng);
     let mut buf: Vec<u8> = vec![];
     assert!(a.serialize(&mut buf).is_ok());
     let b = Poly256::deserialize(&mut buf[..].as_ref()).unwrap();
     assert_eq!(a, b);
 
-    // trinary poly
-    let a: Poly256 = PolyArith::rand_trinary(&mut rng);
+    assert_eq!(buf.len(), Q_POLY_LEN);
+
+    // trinary poly, decoded reduced mod q
+    let mut a: Poly256 = ShortSample::rand_trinary(&mut rng);
     let mut buf: Vec<u8> = vec![];
     assert!(a.serialize(&mut buf).is_ok());
     let b = Poly256::deserialize(&mut buf[..].as_ref()).unwrap();
+    a.normalized();
     assert_eq!(a, b);
 }
 
@@ -187,3 +243,52 @@ fn test_poly256_to_poly32() {
         }
     );
 }
+
+// replays the given words, and then zeros
+struct Replay(Vec<u32>);
+
+impl rand::RngCore for Replay {
+    fn next_u32(&mut self) -> u32 {
+        if self.0.is_empty() {
+            0
+        } else {
+            self.0.remove(0)
+        }
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.next_u32() as u64
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_chacha::rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl rand::CryptoRng for Replay {}
+
+#[test]
+fn test_rejection_range() {
+    // the rejection sampling range itself is rejected, as it is
+    // congruent to 0 and would make the smallest value more likely
+    let a = rand_mod_bound(
+        &mut Replay(vec![BETA_RS_RANGE, 7]),
+        BETA,
+        BETA_M2_P1,
+        BETA_RS_RANGE,
+    );
+    assert_eq!(a.coeff[0], 7 - BETA);
+    let a = rand_mod_bound(
+        &mut Replay(vec![BETA_RS_RANGE - 1]),
+        BETA,
+        BETA_M2_P1,
+        BETA_RS_RANGE,
+    );
+    assert_eq!(a.coeff[0], BETA);
+    let a = Poly256::uniform_random(&mut Replay(vec![Q_RS_RANGE, 7]));
+    assert_eq!(a.coeff[0], 7);
+    let a = Poly32::uniform_random(&mut Replay(vec![P_RS_RANGE, 7]));
+    assert_eq!(a.coeff[0], 7);
+}
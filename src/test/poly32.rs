@@ -1,13 +1,14 @@
-use crate::param::P;
-use crate::poly::PolyArith;
+use crate::param::{P, Q};
+use crate::poly::{PolyArith, ShortSample, UniformSample};
+use crate::poly256::Poly256;
 use crate::poly32::poly32_inner_product;
-use crate::poly32::Poly32;
+use crate::poly32::{school_book, Poly32};
 use crate::serde::Serdes;
 
 #[test]
 fn test_rand_mod_p() {
     let mut rng = rand::thread_rng();
-    let a: Poly32 = PolyArith::uniform_random(&mut rng);
+    let a: Poly32 = UniformSample::uniform_random(&mut rng);
     for e in a.coeff.iter() {
         assert!(*e < Poly32::MODULUS, "coefficient greater than Q")
     }
@@ -54,7 +55,7 @@ fn test_poly32_inner_prod() {
     a.coeff[1] = -1;
     let vec_a = [a; 4];
     let vec_b = [a; 4];
-    let c = poly32_inner_product(vec_a.as_ref(), &vec_b.as_ref());
+    let c = poly32_inner_product(vec_a.as_ref(), vec_b.as_ref());
     println!("{:?}", c);
     assert!(c.coeff[0] == 4);
     assert!(c.coeff[1] == P - 8);
@@ -62,10 +63,7 @@ fn test_poly32_inner_prod() {
     for i in 3..Poly32::DEGREE {
         assert!(c.coeff[i] == 0)
     }
-    println!(
-        "{:?}",
-        a.coeff.iter().map(|x| *x as i32).collect::<Vec<i32>>()
-    );
+    println!("{:?}", a.coeff.to_vec());
 }
 
 #[test]
@@ -79,9 +77,81 @@ fn test_poly32_serdes() {
 
     // random poly
     let mut rng = rand::thread_rng();
-    let a: Poly32 = PolyArith::uniform_random(&mut rng);
+    let a: Poly32 = UniformSample::uniform_random(&mut rng);
     let mut buf: Vec<u8> = vec![];
     assert!(a.serialize(&mut buf).is_ok());
     let b = Poly32::deserialize(&mut buf[..].as_ref()).unwrap();
     assert_eq!(a, b);
 }
+
+#[test]
+fn test_poly32_karatsuba() {
+    let mut rng = rand::thread_rng();
+    let mut edge = Poly32::zero();
+    for (i, e) in edge.coeff.iter_mut().enumerate() {
+        *e = [0, 1, -1, P - 1, 1 - P, P, i32::MAX, i32::MIN][i % 8];
+    }
+    let mut polys = vec![Poly32::zero(), edge];
+    for _ in 0..20 {
+        polys.push(Poly32::uniform_random(&mut rng));
+    }
+    for a in polys.iter() {
+        for b in polys.iter() {
+            let c = Poly32::mul_karatsuba(a, b);
+            // the reference expects reduced inputs
+            let (mut x, mut y) = (*a, *b);
+            x.normalized();
+            y.normalized();
+            let d = school_book(&x, &y);
+            assert_eq!(c, d);
+            assert_eq!(Poly32::mul(a, b), c);
+        }
+    }
+
+    // the inner product matches the sum of the products
+    let a = &polys[2..11];
+    let b = &polys[11..20];
+    let mut expected = Poly32::zero();
+    for (x, y) in a.iter().zip(b.iter()) {
+        expected.add_assign(&school_book(x, y));
+    }
+    expected.normalized();
+    assert_eq!(poly32_inner_product(a, b), expected);
+}
+
+#[test]
+fn test_poly32_from_ntt() {
+    let mut rng = rand::thread_rng();
+    // short polynomials and uniform ones lifted to (-q/2, q/2)
+    let mut a = Poly256::rand_mod_beta(&mut rng);
+    assert_eq!(Poly32::from_ntt(&a.ntt()), Poly32::from(&a));
+    a = Poly256::uniform_random(&mut rng);
+    a.centered();
+    assert_eq!(Poly32::from_ntt(&a.ntt()), Poly32::from(&a));
+    assert!(a.coeff.iter().all(|e| e.abs() <= Q / 2));
+}
+
+// the generic inner product, written once for both rings
+fn inner_product_by_mul<T: PolyArith>(a: &[T], b: &[T]) -> T {
+    let mut res = T::zero();
+    for (x, y) in a.iter().zip(b.iter()) {
+        res.add_assign(&T::mul(x, y));
+    }
+    res
+}
+
+#[test]
+fn test_inner_product_generic() {
+    use crate::poly::TrinaryMul;
+    let mut rng = rand::thread_rng();
+    // longer than the 16 products that poly32_inner_product accumulates at once
+    let a: Vec<Poly32> = (0..40).map(|_| Poly32::uniform_random(&mut rng)).collect();
+    let b: Vec<Poly32> = (0..40).map(|_| Poly32::uniform_random(&mut rng)).collect();
+    assert_eq!(Poly32::inner_product(&a, &b), inner_product_by_mul(&a, &b));
+
+    let a: Vec<Poly256> = (0..9).map(|_| Poly256::uniform_random(&mut rng)).collect();
+    let s: Vec<Poly256> = (0..9).map(|_| Poly256::rand_trinary(&mut rng)).collect();
+    let expected = inner_product_by_mul(&a, &s);
+    assert_eq!(Poly256::inner_product(&a, &s), expected);
+    assert_eq!(Poly256::inner_product_trinary(&a, &s), expected);
+}
@@ -0,0 +1,91 @@
+use crate::keypair::{PublicKey, SecretKey};
+use crate::lbvrf::{Proof, LBVRF};
+use crate::param::{HighSecurity, Param, ParameterSet, Standard};
+use crate::poly::UniformSample;
+use crate::poly256::Poly256;
+use crate::poly32::Poly32;
+use crate::serde::Serdes;
+use crate::VRF;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+fn bytes<T: Serdes>(obj: &T) -> Vec<u8> {
+    let mut buf = vec![];
+    obj.serialize(&mut buf).unwrap();
+    buf
+}
+
+// the object goes through json, bincode and cbor
+fn round_trip<T: Serdes + Serialize + DeserializeOwned + PartialEq + Debug>(obj: &T) {
+    let buf = bytes(obj);
+
+    // human readable: a hex string of the `Serdes` bytes
+    let json = serde_json::to_string(obj).unwrap();
+    assert_eq!(json, format!("\"{}\"", hex::encode(&buf)));
+    assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), obj);
+
+    // binary: the `Serdes` bytes, after a length prefix
+    let bin = bincode::serialize(obj).unwrap();
+    assert_eq!(&bin[8..], &buf[..]);
+    assert_eq!(&bincode::deserialize::<T>(&bin).unwrap(), obj);
+
+    let mut cbor = vec![];
+    ciborium::ser::into_writer(obj, &mut cbor).unwrap();
+    assert!(cbor.ends_with(&buf));
+    assert_eq!(&ciborium::de::from_reader::<T, _>(&cbor[..]).unwrap(), obj);
+
+    // a sequence of bytes is accepted as well
+    let seq = serde_json::to_string(&buf).unwrap();
+    assert_eq!(&serde_json::from_str::<T>(&seq).unwrap(), obj);
+}
+
+fn check_set<S: ParameterSet>() {
+    let param: Param<S> = <LBVRF<S> as VRF>::paramgen([0u8; 32]).unwrap();
+    let (pk, sk) = <LBVRF<S> as VRF>::keygen([1u8; 32], &param).unwrap();
+    let proof = <LBVRF<S> as VRF>::prove("message", &param, &pk, &sk, [2u8; 32]).unwrap();
+    round_trip(&param);
+    round_trip(&pk);
+    round_trip(&sk);
+    round_trip(&proof);
+}
+
+#[test]
+fn test_serde_round_trip() {
+    let mut rng = rand::thread_rng();
+    round_trip(&Poly256::uniform_random(&mut rng));
+    round_trip(&Poly32::uniform_random(&mut rng));
+    round_trip(&Param::<Standard>::init(&mut rng));
+    check_set::<Standard>();
+    check_set::<HighSecurity>();
+}
+
+#[test]
+fn test_serde_strict() {
+    let param: Param = <LBVRF as VRF>::paramgen([0u8; 32]).unwrap();
+    let (pk, sk) = <LBVRF as VRF>::keygen([1u8; 32], &param).unwrap();
+    let proof = <LBVRF as VRF>::prove("message", &param, &pk, &sk, [2u8; 32]).unwrap();
+
+    // trailing and missing bytes
+    let mut buf = bytes(&pk);
+    buf.push(0);
+    let json = format!("\"{}\"", hex::encode(&buf));
+    assert!(serde_json::from_str::<PublicKey>(&json).is_err());
+    // bincode writes a vec of bytes like a byte string
+    let bin = bincode::serialize(&buf).unwrap();
+    assert!(bincode::deserialize::<PublicKey>(&bin).is_err());
+    let json = format!("\"{}\"", hex::encode(&buf[..buf.len() - 2]));
+    assert!(serde_json::from_str::<PublicKey>(&json).is_err());
+
+    // non-canonical encodings and invalid hex
+    let mut buf = bytes(&sk);
+    buf[0] = 0xff;
+    let json = format!("\"{}\"", hex::encode(&buf));
+    let err = serde_json::from_str::<SecretKey>(&json).unwrap_err();
+    assert!(err.to_string().contains("malformed encoding"));
+    let json = serde_json::to_string(&proof).unwrap();
+    assert!(serde_json::from_str::<Proof>(&json.replacen('"', "\"zz", 1)).is_err());
+
+    // the dimensions follow the parameter set
+    assert!(serde_json::from_str::<Proof<HighSecurity>>(&json).is_err());
+}
@@ -0,0 +1,128 @@
+// end to end tests of the command line tool and its exit codes:
+// 0 on success, 1 if a proof is rejected, 2 on any other error
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+const SEED: &str = "0000000000000000000000000000000000000000000000000000000000000007";
+
+// a fresh directory for the files of a test
+fn work_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lb-vrf-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// run the tool in the directory with the given stdin
+fn run(dir: &Path, args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lb-vrf"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn exit_code(output: &Output) -> i32 {
+    output.status.code().unwrap()
+}
+
+#[test]
+fn test_cli_exit_codes() {
+    for format in ["raw", "hex", "pem"].iter() {
+        let dir = work_dir(format);
+        let run = |args: &[&str], stdin: &[u8]| {
+            let mut args = args.to_vec();
+            args.extend_from_slice(&["--format", format]);
+            run(&dir, &args, stdin)
+        };
+
+        let res = run(&["paramgen", "--out", "pp", "--seed", SEED], b"");
+        assert_eq!(exit_code(&res), 0);
+        let res = run(
+            &["keygen", "--param", "pp", "--pk", "pk", "--sk", "sk"],
+            b"",
+        );
+        assert_eq!(exit_code(&res), 0);
+        let res = run(
+            &[
+                "prove", "--param", "pp", "--pk", "pk", "--sk", "sk", "--out", "pi",
+            ],
+            b"message",
+        );
+        assert_eq!(exit_code(&res), 0);
+
+        // accepted: the output is printed on stdout, and nothing else
+        let verify = ["verify", "--param", "pp", "--pk", "pk", "--proof", "pi"];
+        let res = run(&verify, b"message");
+        assert_eq!(exit_code(&res), 0);
+        assert_eq!(res.stdout.len(), 129);
+        assert!(res.stderr.is_empty());
+
+        // rejected
+        let res = run(&verify, b"another message");
+        assert_eq!(exit_code(&res), 1);
+        assert!(res.stdout.is_empty());
+        assert_eq!(res.stderr, b"proof rejected\n");
+
+        // errors: a second proof with the one time key, a missing file,
+        // an invalid command line
+        let res = run(
+            &[
+                "prove", "--param", "pp", "--pk", "pk", "--sk", "sk", "--out", "pi2",
+            ],
+            b"message",
+        );
+        assert_eq!(exit_code(&res), 2);
+        assert!(!dir.join("pi2").exists());
+        let res = run(
+            &[
+                "verify", "--param", "pp", "--pk", "missing", "--proof", "pi",
+            ],
+            b"",
+        );
+        assert_eq!(exit_code(&res), 2);
+        let res = run(&["verify", "--param"], b"");
+        assert_eq!(exit_code(&res), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn test_cli_param_mismatch() {
+    let dir = work_dir("mismatch");
+    let run = |args: &[&str]| run(&dir, args, b"message");
+    assert_eq!(
+        exit_code(&run(&["paramgen", "--out", "pp", "--seed", SEED])),
+        0
+    );
+    assert_eq!(exit_code(&run(&["paramgen", "--out", "pp2"])), 0);
+    let res = run(&["keygen", "--param", "pp", "--pk", "pk", "--sk", "sk"]);
+    assert_eq!(exit_code(&res), 0);
+    let res = run(&[
+        "prove", "--param", "pp", "--pk", "pk", "--sk", "sk", "--out", "pi",
+    ]);
+    assert_eq!(exit_code(&res), 0);
+
+    // the key does not belong to the param: an error, not a rejection
+    let res = run(&["verify", "--param", "pp2", "--pk", "pk", "--proof", "pi"]);
+    assert_eq!(exit_code(&res), 2);
+    assert!(res.stdout.is_empty());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(dir.join("sk")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}